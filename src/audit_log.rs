@@ -0,0 +1,530 @@
+use std::{
+    io::{Read, Result, Seek},
+    ops::Range,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    ChangeBytes, ChangeEvent, ChangeKind, MemOverlay, OverlayError, PatchMetadata, WritePolicy,
+    MAX_EVENT_BYTES,
+};
+
+/// the first bytes of a serialized [`AuditLog`]
+const AUDIT_LOG_MAGIC: &[u8; 8] = b"MOAUDIT1";
+
+/// an [`AuditLog`] which records the changes of an overlay, see
+/// [`AuditLog::attach`]
+pub type SharedAuditLog = Arc<Mutex<AuditLog>>;
+
+/// something which has been recorded in an [`AuditLog`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditRecord {
+    /// the length and the SHA-256 hash of the base as it is stored, without
+    /// any patches and without decoding it. This is always the first record.
+    Base { len: u64, sha256: [u8; 32] },
+
+    /// a change of the data, see [`ChangeEvent`]
+    Change(ChangeEvent),
+}
+
+/// a single link of the hash chain of an [`AuditLog`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub sequence: u64,
+
+    /// the hash of the previous entry, or zeros for the first entry
+    pub previous: [u8; 32],
+    pub record: AuditRecord,
+
+    /// the SHA-256 hash of the sequence number, the previous hash and the
+    /// record
+    pub hash: [u8; 32],
+}
+
+impl AuditEntry {
+    fn new(sequence: u64, previous: [u8; 32], record: AuditRecord) -> Self {
+        let hash = entry_hash(sequence, &previous, &record);
+        Self {
+            sequence,
+            previous,
+            record,
+            hash,
+        }
+    }
+}
+
+/// a tamper-evident, hash-chained log of all changes of an overlay. Every
+/// entry contains the hash of its predecessor, so that changing, removing or
+/// reordering entries breaks the chain.
+///
+/// The log can be verified offline with only the base and the log itself,
+/// see [`AuditLog::replay`].
+///
+/// # Example
+/// ```
+/// use std::io::Cursor;
+/// use memoverlay::{AuditLog, MemOverlay};
+///
+/// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!".to_vec()));
+/// let log = AuditLog::attach(&mut overlay).unwrap();
+/// overlay.add_bytes_at(7, "peter").unwrap();
+///
+/// let bytes = log.lock().unwrap().to_bytes();
+/// let log = AuditLog::from_bytes(&bytes).unwrap();
+/// log.verify().unwrap();
+///
+/// let mut replayed = log.replay(Cursor::new(b"hello, world!".to_vec())).unwrap();
+/// assert_eq!(replayed.read_range(&(0..13)).unwrap(), b"hello, peter!");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    /// creates a log which starts with the fingerprint of the base of
+    /// `overlay`, followed by the encoding of the base and the patches which
+    /// exist already, and records all following changes. Changes inside of
+    /// a transaction are recorded when the transaction is committed.
+    ///
+    /// Changes of more than [`MAX_EVENT_BYTES`] bytes are recorded in parts
+    /// of at most this size which contain all of their bytes, unless they
+    /// are fills, so that the log can always be replayed. From now on, the
+    /// other observers of `overlay` receive these parts as well.
+    pub fn attach<R: Read + Seek>(overlay: &mut MemOverlay<R>) -> Result<SharedAuditLog> {
+        let mut log = Self::default();
+        let (len, sha256) = overlay.hash_base()?;
+        log.append(AuditRecord::Base { len, sha256 });
+
+        // the bytes which have been visible before are not known
+        let mut changes = Vec::new();
+        if overlay.base_encoding().is_some() {
+            changes.push((ChangeKind::Encoding, 0..overlay.data_len(), overlay.current_metadata()));
+        }
+        for (range, _, patch) in overlay.effective_segments() {
+            changes.push((ChangeKind::Write, range, patch.metadata().cloned()));
+        }
+        for (kind, mut rest, metadata) in changes {
+            while !rest.is_empty() {
+                let (range, new_bytes) = overlay.complete_chunk(&rest)?;
+                rest.start = range.end;
+                log.append(AuditRecord::Change(ChangeEvent {
+                    kind,
+                    range,
+                    old_bytes: ChangeBytes::Omitted,
+                    new_bytes,
+                    metadata: metadata.clone(),
+                }));
+            }
+        }
+        overlay.set_complete_events(true);
+
+        let log = Arc::new(Mutex::new(log));
+        let recorder = Arc::clone(&log);
+        overlay.add_observer(move |event| {
+            // entries are appended as a whole, so the log is still consistent
+            // if someone panicked while holding the lock
+            let mut log = recorder.lock().unwrap_or_else(PoisonError::into_inner);
+            log.append(AuditRecord::Change(event.clone()));
+        });
+        Ok(log)
+    }
+
+    fn append(&mut self, record: AuditRecord) {
+        let previous = self.head().unwrap_or([0; 32]);
+        let entry = AuditEntry::new(self.entries.len() as u64, previous, record);
+        self.entries.push(entry);
+    }
+
+    /// creates a log from entries which have been stored elsewhere. This
+    /// does not verify the entries.
+    pub fn from_entries(entries: Vec<AuditEntry>) -> Self {
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &AuditEntry> {
+        self.entries.iter()
+    }
+
+    /// returns the hash of the last entry, which covers the whole chain
+    pub fn head(&self) -> Option<[u8; 32]> {
+        self.entries.last().map(|entry| entry.hash)
+    }
+
+    /// checks that all entries are complete, in order and unchanged
+    pub fn verify(&self) -> std::result::Result<(), OverlayError> {
+        let mut previous = [0; 32];
+        for (index, entry) in self.entries.iter().enumerate() {
+            let failure = if entry.sequence != index as u64 {
+                Some("the entry is out of order")
+            } else if entry.previous != previous {
+                Some("the entry does not follow its predecessor")
+            } else if entry.hash != entry_hash(entry.sequence, &entry.previous, &entry.record) {
+                Some("the entry has been modified")
+            } else if matches!(entry.record, AuditRecord::Base { .. }) != (index == 0) {
+                Some("only the first entry may describe the base")
+            } else {
+                None
+            };
+            if let Some(failure) = failure {
+                return Err(OverlayError::AuditVerificationFailed {
+                    sequence: index as u64,
+                    reason: failure.into(),
+                });
+            }
+            previous = entry.hash;
+        }
+        if self.entries.is_empty() {
+            return Err(OverlayError::AuditVerificationFailed {
+                sequence: 0,
+                reason: "the log is empty".into(),
+            });
+        }
+        Ok(())
+    }
+
+    /// verifies the log, checks that `base` matches the recorded fingerprint
+    /// and replays all changes on top of it. Every change must find the bytes
+    /// it has recorded as old bytes, unless they have been omitted. Changes
+    /// whose new bytes have been omitted cannot be replayed, except for
+    /// reverts, see [`ChangeBytes`]. Returns the resulting overlay.
+    pub fn replay<R: Read + Seek>(&self, base: R) -> Result<MemOverlay<R>> {
+        self.verify()?;
+        let mut overlay = MemOverlay::from(base);
+        overlay.set_write_policy(WritePolicy {
+            allow_gap: true,
+            ..Default::default()
+        });
+
+        for entry in self.entries.iter() {
+            let failure = match &entry.record {
+                AuditRecord::Base { len, sha256 } => {
+                    (overlay.hash_base()? != (*len, *sha256)).then_some("the base does not match its fingerprint")
+                }
+                // the changes of an aborted transaction have never been
+                // recorded, so the old bytes are unknown
+                AuditRecord::Change(event) if event.kind == ChangeKind::Abort => None,
+                AuditRecord::Change(event) => {
+                    if !matches_old_bytes(&mut overlay, &event.range, &event.old_bytes)? {
+                        Some("the change does not match the data")
+                    } else {
+                        // a revert may shorten the data, which a write cannot
+                        if event.kind == ChangeKind::Revert {
+                            overlay.revert(event.range.clone())?;
+                        }
+                        match &event.new_bytes {
+                            _ if event.kind == ChangeKind::Compaction => None,
+                            ChangeBytes::Bytes(bytes) => {
+                                if !bytes.is_empty() {
+                                    overlay.add_bytes_at(event.range.start, bytes)?;
+                                }
+                                None
+                            }
+                            ChangeBytes::Fill { pattern } => {
+                                let len = event.range.end - event.range.start;
+                                overlay.fill_at(event.range.start, pattern, len)?;
+                                None
+                            }
+                            ChangeBytes::Omitted if event.kind == ChangeKind::Revert => None,
+                            ChangeBytes::Omitted => Some("the bytes of the change have not been recorded"),
+                        }
+                    }
+                }
+            };
+            if let Some(failure) = failure {
+                return Err(OverlayError::AuditVerificationFailed {
+                    sequence: entry.sequence,
+                    reason: failure.into(),
+                }
+                .into());
+            }
+        }
+        Ok(overlay)
+    }
+
+    /// signs the head of the chain, which covers all entries
+    #[cfg(feature = "signing")]
+    pub fn sign_head(&self, key: &ed25519_dalek::SigningKey) -> Option<ed25519_dalek::Signature> {
+        use ed25519_dalek::Signer;
+        self.head().map(|head| key.sign(&head))
+    }
+
+    /// verifies the chain and the signature of its head
+    #[cfg(feature = "signing")]
+    pub fn verify_signature(
+        &self,
+        key: &ed25519_dalek::VerifyingKey,
+        signature: &ed25519_dalek::Signature,
+    ) -> std::result::Result<(), OverlayError> {
+        self.verify()?;
+        let head = self.head().unwrap_or_default();
+        key.verify_strict(&head, signature)
+            .map_err(|_| OverlayError::AuditVerificationFailed {
+                sequence: self.entries.len() as u64 - 1,
+                reason: "the signature of the head is invalid".into(),
+            })
+    }
+
+    /// serializes the log, so that it can be stored and verified later
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = AUDIT_LOG_MAGIC.to_vec();
+        put_u64(&mut bytes, self.entries.len() as u64);
+        for entry in self.entries.iter() {
+            encode_entry(&mut bytes, entry.sequence, &entry.previous, &entry.record);
+            bytes.extend(entry.hash);
+        }
+        bytes
+    }
+
+    /// deserializes a log which has been created by [`AuditLog::to_bytes`].
+    /// This does not verify the log.
+    pub fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, OverlayError> {
+        let mut decoder = Decoder { bytes };
+        if decoder.take(AUDIT_LOG_MAGIC.len())? != AUDIT_LOG_MAGIC {
+            return Err(OverlayError::InvalidAuditLog(
+                "this is not an audit log".into(),
+            ));
+        }
+        let count = decoder.u64()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let sequence = decoder.u64()?;
+            let previous = decoder.hash()?;
+            let record = decoder.record()?;
+            let hash = decoder.hash()?;
+            entries.push(AuditEntry {
+                sequence,
+                previous,
+                record,
+                hash,
+            });
+        }
+        if !decoder.bytes.is_empty() {
+            return Err(OverlayError::InvalidAuditLog(
+                "there are trailing bytes".into(),
+            ));
+        }
+        Ok(Self { entries })
+    }
+}
+
+fn entry_hash(sequence: u64, previous: &[u8; 32], record: &AuditRecord) -> [u8; 32] {
+    let mut bytes = Vec::new();
+    encode_entry(&mut bytes, sequence, previous, record);
+    Sha256::digest(&bytes).into()
+}
+
+fn encode_entry(bytes: &mut Vec<u8>, sequence: u64, previous: &[u8; 32], record: &AuditRecord) {
+    put_u64(bytes, sequence);
+    bytes.extend(previous);
+    match record {
+        AuditRecord::Base { len, sha256 } => {
+            bytes.push(0);
+            put_u64(bytes, *len);
+            bytes.extend(sha256);
+        }
+        AuditRecord::Change(event) => {
+            bytes.push(1);
+            bytes.push(match event.kind {
+                ChangeKind::Write => 0,
+                ChangeKind::Revert => 1,
+                ChangeKind::Compaction => 2,
+                ChangeKind::Abort => 3,
+                ChangeKind::Encoding => 4,
+            });
+            put_u64(bytes, event.range.start);
+            put_u64(bytes, event.range.end);
+            put_change_bytes(bytes, &event.old_bytes);
+            put_change_bytes(bytes, &event.new_bytes);
+            match &event.metadata {
+                None => bytes.push(0),
+                Some(metadata) => {
+                    bytes.push(1);
+                    match metadata
+                        .timestamp
+                        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                    {
+                        None => bytes.push(0),
+                        Some(timestamp) => {
+                            bytes.push(1);
+                            put_u64(bytes, timestamp.as_secs());
+                            bytes.extend(timestamp.subsec_nanos().to_le_bytes());
+                        }
+                    }
+                    put_bytes(bytes, metadata.author.as_bytes());
+                    put_bytes(bytes, metadata.reason.as_bytes());
+                    put_u64(bytes, metadata.tags.len() as u64);
+                    for tag in metadata.tags.iter() {
+                        put_bytes(bytes, tag.as_bytes());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn put_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend(value.to_le_bytes());
+}
+
+fn put_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    put_u64(bytes, value.len() as u64);
+    bytes.extend(value);
+}
+
+fn put_change_bytes(bytes: &mut Vec<u8>, value: &ChangeBytes) {
+    match value {
+        ChangeBytes::Bytes(value) => {
+            bytes.push(0);
+            put_bytes(bytes, value);
+        }
+        ChangeBytes::Fill { pattern } => {
+            bytes.push(1);
+            put_bytes(bytes, pattern);
+        }
+        ChangeBytes::Omitted => bytes.push(2),
+    }
+}
+
+/// checks if the visible bytes in `range` are `expected`, reading at most
+/// [`MAX_EVENT_BYTES`] at once
+fn matches_old_bytes<R: Read + Seek>(
+    overlay: &mut MemOverlay<R>,
+    range: &Range<u64>,
+    expected: &ChangeBytes,
+) -> Result<bool> {
+    match expected {
+        ChangeBytes::Bytes(bytes) => Ok(overlay.read_range(range)? == *bytes),
+        ChangeBytes::Fill { pattern } => {
+            let mut offset = range.start;
+            while offset < range.end {
+                let chunk = overlay.read_range(&(offset..range.end.min(offset + MAX_EVENT_BYTES)))?;
+                let shift = ((offset - range.start) % pattern.len() as u64) as usize;
+                let expected = pattern.iter().cycle().skip(shift).take(chunk.len());
+                if chunk.is_empty() || !chunk.iter().eq(expected) {
+                    return Ok(false);
+                }
+                offset += chunk.len() as u64;
+            }
+            Ok(true)
+        }
+        ChangeBytes::Omitted => Ok(true),
+    }
+}
+
+/// reads the values which have been written by [`encode_entry`]
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> std::result::Result<&'a [u8], OverlayError> {
+        if self.bytes.len() < len {
+            return Err(OverlayError::InvalidAuditLog("the log is truncated".into()));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> std::result::Result<u8, OverlayError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> std::result::Result<u64, OverlayError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn hash(&mut self) -> std::result::Result<[u8; 32], OverlayError> {
+        Ok(self.take(32)?.try_into().unwrap())
+    }
+
+    fn bytes(&mut self) -> std::result::Result<Vec<u8>, OverlayError> {
+        let len = self.u64()?;
+        let len = usize::try_from(len)
+            .map_err(|_| OverlayError::InvalidAuditLog("the log is truncated".into()))?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn change_bytes(&mut self) -> std::result::Result<ChangeBytes, OverlayError> {
+        match self.u8()? {
+            0 => Ok(ChangeBytes::Bytes(self.bytes()?)),
+            1 => Ok(ChangeBytes::Fill {
+                pattern: self.bytes()?,
+            }),
+            2 => Ok(ChangeBytes::Omitted),
+            _ => Err(OverlayError::InvalidAuditLog("unknown kind of bytes".into())),
+        }
+    }
+
+    fn string(&mut self) -> std::result::Result<String, OverlayError> {
+        String::from_utf8(self.bytes()?)
+            .map_err(|_| OverlayError::InvalidAuditLog("invalid string".into()))
+    }
+
+    fn record(&mut self) -> std::result::Result<AuditRecord, OverlayError> {
+        match self.u8()? {
+            0 => Ok(AuditRecord::Base {
+                len: self.u64()?,
+                sha256: self.hash()?,
+            }),
+            1 => {
+                let kind = match self.u8()? {
+                    0 => ChangeKind::Write,
+                    1 => ChangeKind::Revert,
+                    2 => ChangeKind::Compaction,
+                    3 => ChangeKind::Abort,
+                    4 => ChangeKind::Encoding,
+                    _ => {
+                        return Err(OverlayError::InvalidAuditLog(
+                            "unknown kind of change".into(),
+                        ))
+                    }
+                };
+                let range = self.u64()?..self.u64()?;
+                let old_bytes = self.change_bytes()?;
+                let new_bytes = self.change_bytes()?;
+                let metadata = match self.u8()? {
+                    0 => None,
+                    _ => {
+                        let timestamp = match self.u8()? {
+                            0 => None,
+                            _ => {
+                                let secs = self.u64()?;
+                                let nanos = u32::from_le_bytes(self.take(4)?.try_into().unwrap());
+                                let timestamp = (nanos < 1_000_000_000)
+                                    .then(|| SystemTime::UNIX_EPOCH.checked_add(Duration::new(secs, nanos)))
+                                    .flatten()
+                                    .ok_or_else(|| OverlayError::InvalidAuditLog("invalid timestamp".into()))?;
+                                Some(timestamp)
+                            }
+                        };
+                        let author = self.string()?;
+                        let reason = self.string()?;
+                        let mut tags = Vec::new();
+                        for _ in 0..self.u64()? {
+                            tags.push(self.string()?);
+                        }
+                        Some(PatchMetadata {
+                            timestamp,
+                            author,
+                            reason,
+                            tags,
+                        })
+                    }
+                };
+                Ok(AuditRecord::Change(ChangeEvent {
+                    kind,
+                    range,
+                    old_bytes,
+                    new_bytes,
+                    metadata,
+                }))
+            }
+            _ => Err(OverlayError::InvalidAuditLog("unknown record".into())),
+        }
+    }
+}
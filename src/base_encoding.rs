@@ -0,0 +1,168 @@
+use std::sync::Mutex;
+
+use crate::OverlayError;
+
+/// describes how the bytes of the base are stored, see
+/// [`crate::MemOverlay::set_base_encoding`]. The overlay shows the decoded
+/// bytes, and all patches contain decoded bytes.
+pub trait BaseEncoding: Send + Sync {
+    /// decodes `bytes`, which are stored at `offset` of the base
+    fn decode(&self, offset: u64, bytes: &mut [u8]);
+
+    /// encodes `bytes`, which are to be stored at `offset`
+    fn encode(&self, offset: u64, bytes: &mut [u8]);
+}
+
+/// xors every byte with a repeating key, which starts at offset 0
+///
+/// # Example
+/// ```
+/// use memoverlay::{BaseEncoding, XorEncoding};
+///
+/// let encoding = XorEncoding::new([0x01, 0x02]);
+/// let mut bytes = [0x00, 0x00, 0x00];
+/// encoding.decode(1, &mut bytes);
+/// assert_eq!(bytes, [0x02, 0x01, 0x02]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct XorEncoding {
+    key: Vec<u8>,
+}
+
+impl XorEncoding {
+    /// creates an encoding which uses `key`. An empty key does not change
+    /// any byte.
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+        }
+    }
+}
+
+impl BaseEncoding for XorEncoding {
+    fn decode(&self, offset: u64, bytes: &mut [u8]) {
+        if self.key.is_empty() {
+            return;
+        }
+        let shift = (offset % self.key.len() as u64) as usize;
+        for (byte, key) in bytes.iter_mut().zip(self.key.iter().cycle().skip(shift)) {
+            *byte ^= key;
+        }
+    }
+
+    fn encode(&self, offset: u64, bytes: &mut [u8]) {
+        self.decode(offset, bytes)
+    }
+}
+
+/// number of keystream bytes between two saved states of an [`Rc4Encoding`]
+const RC4_CHECKPOINT_INTERVAL: u64 = 1024 * 1024;
+
+/// xors every byte with the RC4 keystream, which starts at offset 0.
+///
+/// The keystream can only be generated sequentially, so the encoding saves
+/// its state every MiB to speed up random access.
+///
+/// # Example
+/// ```
+/// use memoverlay::{BaseEncoding, Rc4Encoding};
+///
+/// let encoding = Rc4Encoding::new("Key").unwrap();
+/// let mut bytes = *b"Plaintext";
+/// encoding.encode(0, &mut bytes);
+/// assert_eq!(bytes, [0xbb, 0xf3, 0x16, 0xe8, 0xd9, 0x40, 0xaf, 0x0a, 0xd3]);
+/// encoding.decode(0, &mut bytes);
+/// assert_eq!(&bytes, b"Plaintext");
+/// ```
+pub struct Rc4Encoding {
+    checkpoints: Mutex<Vec<Rc4State>>,
+    current: Mutex<Rc4State>,
+}
+
+#[derive(Clone)]
+struct Rc4State {
+    s: [u8; 256],
+    i: u8,
+    j: u8,
+    pos: u64,
+}
+
+impl Rc4State {
+    fn next(&mut self) -> u8 {
+        self.i = self.i.wrapping_add(1);
+        self.j = self.j.wrapping_add(self.s[self.i as usize]);
+        self.s.swap(self.i as usize, self.j as usize);
+        self.pos += 1;
+        self.s[(self.s[self.i as usize].wrapping_add(self.s[self.j as usize])) as usize]
+    }
+}
+
+impl Rc4Encoding {
+    /// creates an encoding which uses `key`, which must have between 1 and
+    /// 256 bytes
+    pub fn new(key: impl AsRef<[u8]>) -> Result<Self, OverlayError> {
+        let key = key.as_ref();
+        if key.is_empty() || key.len() > 256 {
+            return Err(OverlayError::InvalidKeyLength(key.len()));
+        }
+
+        let mut s = [0u8; 256];
+        for (index, value) in s.iter_mut().enumerate() {
+            *value = index as u8;
+        }
+        let mut j = 0u8;
+        for i in 0..256 {
+            j = j.wrapping_add(s[i]).wrapping_add(key[i % key.len()]);
+            s.swap(i, j as usize);
+        }
+
+        let initial = Rc4State { s, i: 0, j: 0, pos: 0 };
+        Ok(Self {
+            checkpoints: Mutex::new(vec![initial.clone()]),
+            current: Mutex::new(initial),
+        })
+    }
+
+    /// moves `current` to `offset`, starting at the nearest saved state if
+    /// this is faster
+    fn seek_state(&self, offset: u64, current: &mut Rc4State) {
+        if current.pos > offset || offset - current.pos > RC4_CHECKPOINT_INTERVAL {
+            let checkpoints = self.checkpoints.lock().unwrap();
+            let index = std::cmp::min(
+                (offset / RC4_CHECKPOINT_INTERVAL) as usize,
+                checkpoints.len() - 1,
+            );
+            if checkpoints[index].pos > current.pos || current.pos > offset {
+                *current = checkpoints[index].clone();
+            }
+        }
+        while current.pos < offset {
+            current.next();
+            self.save_checkpoint(current);
+        }
+    }
+
+    fn save_checkpoint(&self, state: &Rc4State) {
+        if state.pos.is_multiple_of(RC4_CHECKPOINT_INTERVAL) {
+            let mut checkpoints = self.checkpoints.lock().unwrap();
+            if checkpoints.len() as u64 == state.pos / RC4_CHECKPOINT_INTERVAL {
+                checkpoints.push(state.clone());
+            }
+        }
+    }
+}
+
+impl BaseEncoding for Rc4Encoding {
+    fn decode(&self, offset: u64, bytes: &mut [u8]) {
+        let mut current = self.current.lock().unwrap();
+        self.seek_state(offset, &mut current);
+        for byte in bytes.iter_mut() {
+            *byte ^= current.next();
+            self.save_checkpoint(&current);
+        }
+    }
+
+    fn encode(&self, offset: u64, bytes: &mut [u8]) {
+        self.decode(offset, bytes)
+    }
+}
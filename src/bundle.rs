@@ -0,0 +1,338 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Read, Result, Seek},
+    path::{Component, Path},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{MemOverlay, OverlayError};
+
+/// describes a [`PatchBundle`]. It can be stored as TOML or JSON.
+///
+/// ```toml
+/// name = "fix license check"
+///
+/// [[preconditions]]
+/// kind = "length"
+/// len = 4096
+///
+/// [[preconditions]]
+/// kind = "bytes"
+/// offset = 0
+/// expected = "4d 5a"
+///
+/// [[groups]]
+/// name = "core"
+/// patches = [{ offset = 0x1234, bytes = "eb", expected = "74" }]
+///
+/// [[groups]]
+/// name = "branding"
+/// depends_on = ["core"]
+/// patches = [{ offset = 0x800, payload = "logo.bin" }]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// conditions which must be met by the data before anything is changed
+    #[serde(default)]
+    pub preconditions: Vec<Precondition>,
+
+    #[serde(default)]
+    pub groups: Vec<PatchGroup>,
+}
+
+/// a condition which must be met by the data before a [`PatchBundle`] can be
+/// applied. Hashes and bytes are written as hexadecimal strings.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Precondition {
+    /// the data has exactly `len` bytes
+    Length { len: u64 },
+
+    /// the SHA-256 hash of all of the data is `digest`. Checking it reads and
+    /// hashes all of the data, see [`MemOverlay::hash_view`].
+    Sha256 { digest: String },
+
+    /// the data contains `expected` at `offset`
+    Bytes { offset: u64, expected: String },
+}
+
+/// a named set of patches, which is applied after all groups it depends on
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchGroup {
+    pub name: String,
+
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+
+    #[serde(default)]
+    pub patches: Vec<BundlePatch>,
+}
+
+/// a single patch of a [`PatchGroup`]. Its content is given either inline as
+/// hexadecimal `bytes`, or as the name of a `payload` of the bundle.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundlePatch {
+    pub offset: u64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+
+    /// the bytes which must be replaced by this patch, see
+    /// [`MemOverlay::write_if_matches`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+}
+
+/// a set of patch groups together with the binary payloads they use.
+///
+/// All preconditions are checked before anything is changed, and all groups
+/// are applied atomically, in the order of their dependencies.
+///
+/// # Example
+/// ```
+/// use std::io::Cursor;
+/// use memoverlay::{MemOverlay, PatchBundle};
+///
+/// let mut bundle = PatchBundle::from_json(r#"{
+///     "name": "greeting",
+///     "preconditions": [{ "kind": "length", "len": 13 }],
+///     "groups": [
+///         { "name": "name", "depends_on": ["punctuation"],
+///           "patches": [{ "offset": 7, "payload": "name", "expected": "776f726c64" }] },
+///         { "name": "punctuation", "patches": [{ "offset": 12, "bytes": "3f" }] }
+///     ]
+/// }"#).unwrap();
+/// bundle.add_payload("name", "peter");
+///
+/// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+/// bundle.apply(&mut overlay).unwrap();
+/// assert_eq!(overlay.read_range(&(0..13)).unwrap(), b"hello, peter?");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PatchBundle {
+    manifest: BundleManifest,
+    payloads: HashMap<String, Vec<u8>>,
+}
+
+impl PatchBundle {
+    pub fn new(manifest: BundleManifest) -> Self {
+        Self {
+            manifest,
+            payloads: HashMap::new(),
+        }
+    }
+
+    pub fn from_json(manifest: &str) -> std::result::Result<Self, OverlayError> {
+        serde_json::from_str(manifest)
+            .map(Self::new)
+            .map_err(|why| OverlayError::InvalidBundle(why.to_string()))
+    }
+
+    pub fn from_toml(manifest: &str) -> std::result::Result<Self, OverlayError> {
+        toml::from_str(manifest)
+            .map(Self::new)
+            .map_err(|why| OverlayError::InvalidBundle(why.to_string()))
+    }
+
+    /// loads a bundle from a directory, which contains either a
+    /// `manifest.toml` or a `manifest.json`. The names of all payloads are
+    /// paths relative to this directory; absolute paths and paths which
+    /// contain `..` are rejected.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let toml_manifest = dir.join("manifest.toml");
+        let mut bundle = if toml_manifest.exists() {
+            Self::from_toml(&fs::read_to_string(toml_manifest)?)?
+        } else {
+            Self::from_json(&fs::read_to_string(dir.join("manifest.json"))?)?
+        };
+
+        let names: Vec<_> = bundle
+            .manifest
+            .groups
+            .iter()
+            .flat_map(|group| group.patches.iter())
+            .filter_map(|patch| patch.payload.clone())
+            .collect();
+        for name in names {
+            let path = Path::new(&name);
+            let relative = path.components().all(|component| matches!(component, Component::Normal(_)));
+            if name.is_empty() || !relative {
+                return Err(OverlayError::InvalidBundle(format!(
+                    "the payload '{name}' is not a relative path inside of the bundle"
+                ))
+                .into());
+            }
+            let payload = fs::read(dir.join(path))?;
+            bundle.payloads.insert(name, payload);
+        }
+        Ok(bundle)
+    }
+
+    pub fn manifest(&self) -> &BundleManifest {
+        &self.manifest
+    }
+
+    pub fn add_payload(&mut self, name: impl Into<String>, payload: impl AsRef<[u8]>) {
+        self.payloads.insert(name.into(), payload.as_ref().to_vec());
+    }
+
+    pub fn payload(&self, name: &str) -> Option<&[u8]> {
+        self.payloads.get(name).map(|payload| &payload[..])
+    }
+
+    /// returns all groups in the order in which they are applied. A group is
+    /// applied after all groups it depends on, and otherwise in the order of
+    /// the manifest.
+    pub fn ordered_groups(&self) -> std::result::Result<Vec<&PatchGroup>, OverlayError> {
+        let groups = &self.manifest.groups;
+        let mut indices = HashMap::new();
+        for (index, group) in groups.iter().enumerate() {
+            if indices.insert(group.name.as_str(), index).is_some() {
+                return Err(OverlayError::InvalidBundle(format!(
+                    "there is more than one group named '{}'",
+                    group.name
+                )));
+            }
+        }
+
+        let mut ordered = Vec::new();
+        let mut state = vec![VisitState::New; groups.len()];
+        for index in 0..groups.len() {
+            visit(groups, &indices, index, &mut state, &mut ordered)?;
+        }
+        Ok(ordered.into_iter().map(|index| &groups[index]).collect())
+    }
+
+    /// checks if all preconditions are met by the currently visible data
+    pub fn check<R: Read + Seek>(&self, overlay: &mut MemOverlay<R>) -> Result<()> {
+        for precondition in self.manifest.preconditions.iter() {
+            let failure = match precondition {
+                Precondition::Length { len } => (overlay.data_len() != *len)
+                    .then(|| format!("the data has {} bytes instead of {len}", overlay.data_len())),
+                Precondition::Sha256 { digest } => {
+                    let expected = decode_hex(digest)?;
+                    let actual = overlay.hash_view()?;
+                    (actual[..] != expected[..]).then(|| {
+                        format!("the SHA-256 hash is {} instead of {digest}", hex::encode(actual))
+                    })
+                }
+                Precondition::Bytes { offset, expected } => {
+                    let expected = decode_hex(expected)?;
+                    let end = offset.checked_add(expected.len() as u64).ok_or_else(|| {
+                        OverlayError::InvalidBundle(format!(
+                            "the expected bytes at offset {offset:#x} end beyond the largest offset"
+                        ))
+                    })?;
+                    let actual = overlay.read_range(&(*offset..end))?;
+                    (actual != expected).then(|| {
+                        format!(
+                            "expected {} at offset {offset:#x}, but found {}",
+                            hex::encode(&expected),
+                            hex::encode(&actual)
+                        )
+                    })
+                }
+            };
+            if let Some(failure) = failure {
+                return Err(OverlayError::PreconditionFailed(failure).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// checks all preconditions and applies all groups atomically
+    pub fn apply<R: Read + Seek>(&self, overlay: &mut MemOverlay<R>) -> Result<()> {
+        // resolve everything before changing any data
+        let mut patches = Vec::new();
+        for group in self.ordered_groups()? {
+            for patch in group.patches.iter() {
+                let bytes = match (&patch.bytes, &patch.payload) {
+                    (Some(bytes), None) => decode_hex(bytes)?,
+                    (None, Some(name)) => self
+                        .payload(name)
+                        .ok_or_else(|| {
+                            OverlayError::InvalidBundle(format!("the payload '{name}' is missing"))
+                        })?
+                        .to_vec(),
+                    _ => {
+                        return Err(OverlayError::InvalidBundle(format!(
+                            "the patch at {:#x} in group '{}' needs either bytes or a payload",
+                            patch.offset, group.name
+                        ))
+                        .into())
+                    }
+                };
+                let expected = patch.expected.as_deref().map(decode_hex).transpose()?;
+                patches.push((patch.offset, bytes, expected));
+            }
+        }
+
+        self.check(overlay)?;
+        overlay.atomically(|overlay| {
+            for (offset, bytes, expected) in patches.iter() {
+                match expected {
+                    Some(expected) => overlay.write_if_matches(*offset, expected, bytes)?,
+                    None => overlay.write_at(*offset, bytes).map(|_| ())?,
+                }
+            }
+            Ok::<_, std::io::Error>(())
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VisitState {
+    New,
+    Visiting,
+    Done,
+}
+
+/// appends the group at `index` to `ordered`, after all of its dependencies
+fn visit(
+    groups: &[PatchGroup],
+    indices: &HashMap<&str, usize>,
+    index: usize,
+    state: &mut [VisitState],
+    ordered: &mut Vec<usize>,
+) -> std::result::Result<(), OverlayError> {
+    match state[index] {
+        VisitState::Done => return Ok(()),
+        VisitState::Visiting => {
+            return Err(OverlayError::InvalidBundle(format!(
+                "the group '{}' depends on itself",
+                groups[index].name
+            )))
+        }
+        VisitState::New => (),
+    }
+
+    state[index] = VisitState::Visiting;
+    for dependency in groups[index].depends_on.iter() {
+        let dependency = *indices.get(dependency.as_str()).ok_or_else(|| {
+            OverlayError::InvalidBundle(format!(
+                "the group '{}' depends on the unknown group '{dependency}'",
+                groups[index].name
+            ))
+        })?;
+        visit(groups, indices, dependency, state, ordered)?;
+    }
+    state[index] = VisitState::Done;
+    ordered.push(index);
+    Ok(())
+}
+
+fn decode_hex(bytes: &str) -> std::result::Result<Vec<u8>, OverlayError> {
+    let digits: String = bytes.chars().filter(|c| !c.is_whitespace()).collect();
+    hex::decode(digits).map_err(|why| OverlayError::InvalidBundle(format!("'{bytes}': {why}")))
+}
@@ -0,0 +1,107 @@
+/// computes the content of a patch from the bytes beneath it, see
+/// [`crate::Patch::computed`]
+pub trait ByteTransform: Send + Sync {
+    /// transforms `bytes` in place. `bytes` contains all bytes beneath the
+    /// patch, even if only a part of it is read
+    fn transform(&self, bytes: &mut [u8]);
+}
+
+impl<F> ByteTransform for F
+where
+    F: Fn(&mut [u8]) + Send + Sync,
+{
+    fn transform(&self, bytes: &mut [u8]) {
+        self(bytes)
+    }
+}
+
+/// xors the bytes with a repeating key
+///
+/// # Example
+/// ```
+/// use memoverlay::{ByteTransform, Xor};
+///
+/// let mut bytes = [0x00, 0x0f, 0xf0];
+/// Xor::new([0xff, 0x0f]).transform(&mut bytes);
+/// assert_eq!(bytes, [0xff, 0x00, 0x0f]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Xor {
+    key: Vec<u8>,
+}
+
+impl Xor {
+    /// creates a transform which uses `key`. An empty key does not change
+    /// any byte.
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+        }
+    }
+}
+
+impl ByteTransform for Xor {
+    fn transform(&self, bytes: &mut [u8]) {
+        for (byte, key) in bytes.iter_mut().zip(self.key.iter().cycle()) {
+            *byte ^= key;
+        }
+    }
+}
+
+/// interprets all bytes as a single little endian integer and adds `delta`
+/// to it. The result wraps around on overflow.
+///
+/// # Example
+/// ```
+/// use memoverlay::{AddLe, ByteTransform};
+///
+/// let mut bytes = [0xff, 0x00, 0x01];
+/// AddLe(1).transform(&mut bytes);
+/// assert_eq!(bytes, [0x00, 0x01, 0x01]);
+/// AddLe(-2).transform(&mut bytes);
+/// assert_eq!(bytes, [0xfe, 0x00, 0x01]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AddLe(pub i64);
+
+impl ByteTransform for AddLe {
+    fn transform(&self, bytes: &mut [u8]) {
+        add_le(bytes.iter_mut(), self.0)
+    }
+}
+
+/// interprets all bytes as a single big endian integer and adds `delta` to
+/// it. The result wraps around on overflow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AddBe(pub i64);
+
+impl ByteTransform for AddBe {
+    fn transform(&self, bytes: &mut [u8]) {
+        add_le(bytes.iter_mut().rev(), self.0)
+    }
+}
+
+/// reverses the order of all bytes, which converts an integer from little
+/// endian to big endian and vice versa
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ByteSwap;
+
+impl ByteTransform for ByteSwap {
+    fn transform(&self, bytes: &mut [u8]) {
+        bytes.reverse()
+    }
+}
+
+/// adds `delta` to the integer whose bytes are returned by `bytes`, starting
+/// with the least significant byte
+fn add_le<'a>(bytes: impl Iterator<Item = &'a mut u8>, delta: i64) {
+    let extension = if delta < 0 { 0xff } else { 0x00 };
+    let delta = delta.to_le_bytes();
+    let mut carry = 0u16;
+    for (index, byte) in bytes.enumerate() {
+        let summand = delta.get(index).copied().unwrap_or(extension);
+        let sum = u16::from(*byte) + u16::from(summand) + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+}
@@ -0,0 +1,87 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::PatchMetadata;
+
+/// the kind of operation which changed the data of a [`crate::MemOverlay`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// bytes have been written
+    Write,
+
+    /// patches have been removed, see [`crate::MemOverlay::revert`]
+    Revert,
+
+    /// patch layers have been merged, see [`crate::MemOverlay::compact`].
+    /// This does not change any visible byte.
+    Compaction,
+
+    /// a transaction has been aborted, see [`crate::MemOverlay::abort`].
+    /// `old_bytes` are the bytes which were visible inside of the
+    /// transaction.
+    Abort,
+
+    /// the encoding of the base has been changed, see
+    /// [`crate::MemOverlay::set_base_encoding`]
+    Encoding,
+}
+
+/// describes a change of the data of a [`crate::MemOverlay`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    pub range: Range<u64>,
+
+    /// the visible bytes in `range` before the change
+    pub old_bytes: ChangeBytes,
+
+    /// the visible bytes in `range` after the change
+    pub new_bytes: ChangeBytes,
+
+    /// the metadata of the written patch, or the metadata of the overlay
+    /// for other changes, see [`crate::MemOverlay::set_metadata`]
+    pub metadata: Option<PatchMetadata>,
+}
+
+/// the largest number of bytes which are copied into a [`ChangeEvent`]
+pub const MAX_EVENT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// the visible bytes in the range of a [`ChangeEvent`]. Ranges with more
+/// than [`MAX_EVENT_BYTES`] bytes are not copied, so that e.g. a large fill
+/// does not need memory for all of its bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChangeBytes {
+    /// all bytes of the range
+    Bytes(Vec<u8>),
+
+    /// the range is too large, and shows `pattern` repeatedly, starting with
+    /// its first byte, see [`crate::MemOverlay::fill_at`]
+    Fill { pattern: Vec<u8> },
+
+    /// the range is too large, and its bytes are not known. Overlays with an
+    /// attached audit log split such changes into several events instead,
+    /// but their old bytes may still be omitted.
+    Omitted,
+}
+
+impl ChangeBytes {
+    /// returns all bytes of the range, if they are known
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            ChangeBytes::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> PartialEq<T> for ChangeBytes {
+    fn eq(&self, other: &T) -> bool {
+        self.as_bytes() == Some(other.as_ref())
+    }
+}
+
+/// a callback which is invoked for every change of a [`crate::MemOverlay`]
+pub type Observer = Arc<dyn Fn(&ChangeEvent) + Send + Sync>;
+
+/// identifies a registered [`Observer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObserverId(pub(crate) usize);
@@ -0,0 +1,116 @@
+use std::ops::Range;
+
+use crate::RangeSet;
+
+/// remembers which parts of the data have changed since the last
+/// checkpoint, see [`crate::MemOverlay::enable_change_tracking`]
+#[derive(Clone, Debug)]
+pub(crate) struct ChangeTracker {
+    pub(crate) block_size: u64,
+
+    /// the length of the data at the last checkpoint
+    pub(crate) checkpoint_len: u64,
+    pub(crate) changed: RangeSet,
+}
+
+impl ChangeTracker {
+    pub(crate) fn new(block_size: u64, data_len: u64) -> Self {
+        Self {
+            block_size,
+            checkpoint_len: data_len,
+            changed: RangeSet::default(),
+        }
+    }
+
+    /// returns the blocks which have changed, if the data has `data_len`
+    /// bytes now. Blocks which did not exist at the checkpoint, or whose
+    /// length has changed, are changed as well.
+    pub(crate) fn bitmap(&self, data_len: u64) -> DirtyBitmap {
+        let mut bitmap = DirtyBitmap::new(self.block_size, data_len);
+        for range in self.changed.iter() {
+            bitmap.mark(range);
+        }
+        if data_len != self.checkpoint_len {
+            let begin = self.checkpoint_len.min(data_len) / self.block_size * self.block_size;
+            bitmap.mark(begin..data_len);
+        }
+        bitmap
+    }
+}
+
+/// one bit for every block of the data, which is set if the block has
+/// changed since the last checkpoint, see
+/// [`crate::MemOverlay::dirty_bitmap`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirtyBitmap {
+    block_size: u64,
+    data_len: u64,
+
+    /// the bit of block `n` is bit `n % 8` of byte `n / 8`
+    bits: Vec<u8>,
+}
+
+impl DirtyBitmap {
+    fn new(block_size: u64, data_len: u64) -> Self {
+        let block_count = data_len.div_ceil(block_size);
+        Self {
+            block_size,
+            data_len,
+            bits: vec![0; block_count.div_ceil(8) as usize],
+        }
+    }
+
+    /// sets the bits of all blocks which overlap `range`
+    fn mark(&mut self, range: Range<u64>) {
+        let first = range.start / self.block_size;
+        let last = range.end.div_ceil(self.block_size).min(self.block_count());
+        for index in first..last {
+            self.bits[(index / 8) as usize] |= 1 << (index % 8);
+        }
+    }
+
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// returns the length of the data when the bitmap has been created
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    pub fn block_count(&self) -> u64 {
+        self.data_len.div_ceil(self.block_size)
+    }
+
+    pub fn is_dirty(&self, index: u64) -> bool {
+        index < self.block_count() && self.bits[(index / 8) as usize] & (1 << (index % 8)) != 0
+    }
+
+    /// returns the indices of all changed blocks in ascending order
+    pub fn dirty_blocks(&self) -> impl Iterator<Item = u64> + '_ {
+        (0..self.block_count()).filter(|index| self.is_dirty(*index))
+    }
+
+    pub fn dirty_count(&self) -> u64 {
+        self.bits.iter().map(|byte| byte.count_ones() as u64).sum()
+    }
+
+    /// returns the range of the bytes in the block with the given index
+    pub fn block_range(&self, index: u64) -> Range<u64> {
+        let begin = index * self.block_size;
+        begin..(begin + self.block_size).min(self.data_len)
+    }
+
+    /// returns the raw bits, see [`DirtyBitmap`]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits
+    }
+}
+
+/// the current content of a block which has changed since the last
+/// checkpoint, see [`crate::MemOverlay::export_changed_blocks`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangedBlock {
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+}
@@ -1,7 +1,138 @@
+use std::io;
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum OverlayError {
     #[error("this patch contains no data, which makes no sense")]
-    EmptyPatch
-}
\ No newline at end of file
+    EmptyPatch,
+
+    #[error("{len} bytes at offset {offset:#x} would end beyond the largest possible offset")]
+    OffsetOverflow { offset: u64, len: u64 },
+
+    #[error("writing {len} bytes at offset {offset:#x} would overlap an existing patch")]
+    OverlappingWrite { offset: u64, len: u64 },
+
+    #[error("writing {len} bytes at offset {offset:#x} would grow the data beyond the base length of {base_len} bytes")]
+    GrowthForbidden { offset: u64, len: u64, base_len: u64 },
+
+    #[error("writing would require {required} bytes of patch data, but only {limit} bytes are allowed")]
+    PatchLimitExceeded { required: u64, limit: u64 },
+
+    #[error("writing at offset {offset:#x} would leave a gap behind the end of data at {end:#x}")]
+    WriteBeyondEnd { offset: u64, end: u64 },
+
+    #[error("changing {len} bytes at offset {offset:#x} would modify the protected region {begin:#x}..{end:#x}")]
+    ProtectedRegion { offset: u64, len: u64, begin: u64, end: u64 },
+
+    #[error("there is already an active transaction")]
+    TransactionActive,
+
+    #[error("there is no active transaction")]
+    NoTransaction,
+
+    #[error("the character {0:?} cannot be encoded")]
+    UnencodableCharacter(char),
+
+    #[error("the string needs {len} bytes, but there is only space for {capacity} bytes")]
+    StringTooLong { len: usize, capacity: usize },
+
+    #[error("the string needs {len} bytes, but must fill exactly {width} bytes")]
+    StringTooShort { len: usize, width: usize },
+
+    #[error("the data does not contain a valid string")]
+    InvalidString,
+
+    #[error("invalid search pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("the match at offset {offset:#x} has {match_len} bytes, but the replacement has {replacement_len} bytes")]
+    ReplacementLengthMismatch { offset: u64, match_len: u64, replacement_len: u64 },
+
+    #[error("cannot copy {begin:#x}..{end:#x}, because the data ends at {data_len:#x}")]
+    CopySourceOutOfRange { begin: u64, end: u64, data_len: u64 },
+
+    #[error("cannot create a live copy of {begin:#x}..{end:#x} at {offset:#x}, because it would refer to itself")]
+    OverlappingCopy { begin: u64, end: u64, offset: u64 },
+
+    #[error("cannot create a live copy of {begin:#x}..{end:#x} at {offset:#x}, because other live copies already show the copy in its source")]
+    CyclicCopy { begin: u64, end: u64, offset: u64 },
+
+    #[error("more than {0} nested references must be followed to read the data")]
+    ReferenceDepthExceeded(usize),
+
+    #[error("the value has {value_len} bytes, but the mask has {mask_len} bytes")]
+    MaskLengthMismatch { value_len: usize, mask_len: usize },
+
+    #[error("a key with {0} bytes cannot be used")]
+    InvalidKeyLength(usize),
+
+    #[error("expected {expected:02x?} at offset {offset:#x}, but found {actual:02x?}")]
+    UnexpectedBytes { offset: u64, expected: Vec<u8>, actual: Vec<u8> },
+
+    #[error("the signature does not match")]
+    SignatureNotFound,
+
+    #[error("the signature matches more than once, at {offsets:#x?}")]
+    AmbiguousSignature { offsets: Vec<u64> },
+
+    #[error("cannot move {delta} bytes from the signature at {offset:#x}")]
+    TargetOutOfRange { offset: u64, delta: i64 },
+
+    #[error("invalid patch bundle: {0}")]
+    InvalidBundle(String),
+
+    #[error("precondition failed: {0}")]
+    PreconditionFailed(String),
+
+    #[error("invalid block size {0}")]
+    InvalidBlockSize(u64),
+
+    #[error("invalid audit log: {0}")]
+    InvalidAuditLog(String),
+
+    #[error("the audit log is not valid at entry {sequence}: {reason}")]
+    AuditVerificationFailed { sequence: u64, reason: String },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl From<OverlayError> for io::Error {
+    fn from(err: OverlayError) -> Self {
+        let kind = match err {
+            OverlayError::Io(err) => return err,
+            OverlayError::EmptyPatch => io::ErrorKind::InvalidData,
+            OverlayError::OverlappingWrite { .. }
+            | OverlayError::GrowthForbidden { .. }
+            | OverlayError::PatchLimitExceeded { .. }
+            | OverlayError::WriteBeyondEnd { .. }
+            | OverlayError::ProtectedRegion { .. } => io::ErrorKind::PermissionDenied,
+            OverlayError::OffsetOverflow { .. }
+            | OverlayError::TransactionActive
+            | OverlayError::NoTransaction
+            | OverlayError::UnencodableCharacter(_)
+            | OverlayError::StringTooLong { .. }
+            | OverlayError::StringTooShort { .. }
+            | OverlayError::InvalidPattern(_)
+            | OverlayError::ReplacementLengthMismatch { .. }
+            | OverlayError::CopySourceOutOfRange { .. }
+            | OverlayError::OverlappingCopy { .. }
+            | OverlayError::CyclicCopy { .. }
+            | OverlayError::MaskLengthMismatch { .. }
+            | OverlayError::InvalidKeyLength(_)
+            | OverlayError::InvalidBlockSize(_) => io::ErrorKind::InvalidInput,
+            OverlayError::InvalidString
+            | OverlayError::ReferenceDepthExceeded(_)
+            | OverlayError::UnexpectedBytes { .. }
+            | OverlayError::AmbiguousSignature { .. }
+            | OverlayError::TargetOutOfRange { .. }
+            | OverlayError::InvalidBundle(_)
+            | OverlayError::PreconditionFailed(_)
+            | OverlayError::InvalidAuditLog(_)
+            | OverlayError::AuditVerificationFailed { .. } => io::ErrorKind::InvalidData,
+            OverlayError::SignatureNotFound => io::ErrorKind::NotFound,
+        };
+        io::Error::new(kind, err)
+    }
+}
@@ -33,19 +33,59 @@
 //! # }
 //! ```
 
+#[cfg(feature = "audit")]
+mod audit_log;
+mod base_encoding;
+#[cfg(feature = "bundle")]
+mod bundle;
+mod byte_transform;
+mod change_event;
+mod dirty_bitmap;
 mod memoverlay;
+#[cfg(feature = "hash")]
+mod merkle_tree;
 mod patch;
 mod traits;
 mod error;
 mod patch_layer;
+mod patch_metadata;
 mod patch_search_result;
+mod range_set;
+mod redaction;
+mod search_pattern;
+mod signature_patch;
+mod stream_base;
+mod string_format;
+#[cfg(feature = "zerocopy")]
+mod struct_view;
+mod write_policy;
 
 pub use crate::memoverlay::*;
+#[cfg(feature = "hash")]
+pub use merkle_tree::*;
+#[cfg(feature = "audit")]
+pub use audit_log::*;
+pub use base_encoding::*;
+#[cfg(feature = "bundle")]
+pub use bundle::*;
+pub use byte_transform::*;
+pub use change_event::*;
+pub use dirty_bitmap::*;
 pub use patch::*;
 pub use traits::*;
 pub use error::*;
 pub use patch_layer::*;
+pub use patch_metadata::*;
 pub use patch_search_result::*;
+pub use range_set::*;
+pub use redaction::*;
+pub use search_pattern::*;
+pub use signature_patch::*;
+pub use stream_base::*;
+pub use string_format::*;
+#[cfg(feature = "zerocopy")]
+pub use struct_view::*;
+pub use write_policy::*;
 
 #[macro_export]
 macro_rules! overlay {
@@ -108,6 +148,19 @@ mod tests {
         assert_eq!(message2, "helXXXXYYrld!");
     }
 
+    #[test]
+    fn mod_below_overlapping_layers() {
+        let message1 = "hello, world!";
+        let mut overlay = overlay!(message1.as_bytes());
+        overlay.add_bytes_at(3, "XXXX".as_bytes()).unwrap();
+        overlay.add_bytes_at(5, "YYYY".as_bytes()).unwrap();
+        overlay.add_bytes_at(8, "ZZ".as_bytes()).unwrap();
+
+        let mut message2 = String::new();
+        let _ = overlay.read_to_string(&mut message2).unwrap();
+        assert_eq!(message2, "helXXYYYZZld!");
+    }
+
     #[test]
     fn doc_test() {
         let message1 = "hello, world!";
@@ -0,0 +1,118 @@
+use std::{
+    cmp::{max, min},
+    io::{Read, Seek},
+    ops::Range,
+    time::SystemTime,
+};
+
+use crate::{BlameEntry, BlameSource, MemOverlay, Patch, PatchMetadata};
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// attaches `metadata` to all following writes, which do not have their
+    /// own metadata. `None` stops attaching metadata.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::{BlameSource, MemOverlay, PatchMetadata};
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    /// overlay.set_metadata(Some(PatchMetadata::new("alice").with_reason("greet peter")));
+    /// overlay.add_bytes_at(7, "peter").unwrap();
+    ///
+    /// assert!(matches!(overlay.blame(0), Some(BlameSource::Base)));
+    /// let source = overlay.blame(8).unwrap();
+    /// assert_eq!(source.metadata().unwrap().author, "alice");
+    /// assert!(source.metadata().unwrap().timestamp.is_some());
+    /// ```
+    pub fn set_metadata(&mut self, metadata: Option<PatchMetadata>) {
+        self.metadata = metadata;
+    }
+
+    pub fn metadata(&self) -> Option<&PatchMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// returns where the visible byte at `offset` comes from, or `None` if
+    /// `offset` lies beyond the end of the data
+    pub fn blame(&self, offset: u64) -> Option<BlameSource> {
+        self.blame_range(offset..offset.checked_add(1)?)
+            .into_iter()
+            .next()
+            .map(|entry| entry.source)
+    }
+
+    /// splits `range` into parts whose bytes come from the same source. The
+    /// result does not reach beyond the end of the data.
+    pub fn blame_range(&self, range: Range<u64>) -> Vec<BlameEntry> {
+        let end = min(range.end, self.data_len());
+        let mut entries = Vec::new();
+        let mut pos = range.start;
+
+        for (segment, layer, patch) in self.effective_segments() {
+            if segment.end <= pos {
+                continue;
+            }
+            if segment.start >= end {
+                break;
+            }
+            self.blame_unpatched(pos..segment.start, &mut entries);
+            let begin = max(pos, segment.start);
+            pos = min(end, segment.end);
+            entries.push(BlameEntry {
+                range: begin..pos,
+                source: BlameSource::Patch {
+                    layer,
+                    patch: patch.clone(),
+                },
+            });
+        }
+        self.blame_unpatched(pos..end, &mut entries);
+        entries
+    }
+
+    /// adds entries for a range which is not covered by any patch
+    fn blame_unpatched(&self, range: Range<u64>, entries: &mut Vec<BlameEntry>) {
+        let split = range.end.clamp(range.start, max(range.start, self.base_len));
+        if range.start < split {
+            entries.push(BlameEntry {
+                range: range.start..split,
+                source: BlameSource::Base,
+            });
+        }
+        if split < range.end {
+            entries.push(BlameEntry {
+                range: split..range.end,
+                source: BlameSource::Gap,
+            });
+        }
+    }
+
+    /// returns the metadata of the overlay with the current time, if it has
+    /// no timestamp
+    pub(crate) fn current_metadata(&self) -> Option<PatchMetadata> {
+        self.metadata.as_ref().map(|metadata| {
+            let timestamp = metadata.timestamp.unwrap_or_else(SystemTime::now);
+            metadata.clone().with_timestamp(timestamp)
+        })
+    }
+
+    /// attaches the current metadata to `patch`, if it has no metadata yet,
+    /// and sets its timestamp
+    pub(crate) fn attach_metadata(&self, patch: Patch) -> Patch {
+        match patch.metadata() {
+            Some(metadata) if metadata.timestamp.is_some() => patch,
+            Some(metadata) => {
+                let metadata = metadata.clone().with_timestamp(SystemTime::now());
+                patch.with_metadata(metadata)
+            }
+            None => match self.current_metadata() {
+                Some(metadata) => patch.with_metadata(metadata),
+                None => patch,
+            },
+        }
+    }
+}
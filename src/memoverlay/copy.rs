@@ -0,0 +1,82 @@
+use std::{
+    io::{Read, Result, Seek},
+    ops::Range,
+    sync::Arc,
+};
+
+use crate::{patch::Snapshot, CopyMode, MemOverlay, OverlayError, Patch};
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// makes the bytes in `source` visible at `offset` as well, without
+    /// copying them. `mode` defines what happens if the bytes in `source`
+    /// are changed later.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::{CopyMode, MemOverlay};
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"abcd____"));
+    /// overlay.copy_within(0..4, 4, CopyMode::Snapshot).unwrap();
+    /// overlay.add_bytes_at(0, "x").unwrap();
+    /// assert_eq!(overlay.read_range(&(0..8)).unwrap(), b"xbcdabcd");
+    /// ```
+    pub fn copy_within(&mut self, source: Range<u64>, offset: u64, mode: CopyMode) -> Result<()> {
+        if source.end > self.data_len() && self.base_len_is_stale() {
+            self.refresh_base_len()?;
+        }
+        if source.end > self.data_len() {
+            return Err(OverlayError::CopySourceOutOfRange {
+                begin: source.start,
+                end: source.end,
+                data_len: self.data_len(),
+            }
+            .into());
+        }
+
+        let len = source.end.saturating_sub(source.start);
+        let end = offset
+            .checked_add(len)
+            .ok_or(OverlayError::OffsetOverflow { offset, len })?;
+        let snapshot = match mode {
+            CopyMode::Snapshot => Some(Arc::new(Snapshot {
+                layers: self.patch_layers.clone(),
+                base_len: self.base_len,
+                base_encoding: self.base_encoding.clone(),
+            })),
+            CopyMode::Live => {
+                if source.start < end && offset < source.end {
+                    return Err(OverlayError::OverlappingCopy {
+                        begin: source.start,
+                        end: source.end,
+                        offset,
+                    }
+                    .into());
+                }
+
+                // the source must not show the bytes at `offset` yet, or the
+                // copy would show itself
+                if self
+                    .with_live_copies(offset..end)
+                    .overlapping(&source)
+                    .next()
+                    .is_some()
+                {
+                    return Err(OverlayError::CyclicCopy {
+                        begin: source.start,
+                        end: source.end,
+                        offset,
+                    }
+                    .into());
+                }
+                None
+            }
+        };
+
+        let patch = Patch::reference(offset, source.start, len, snapshot)?;
+        self.add_patch(patch)
+    }
+}
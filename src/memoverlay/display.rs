@@ -8,7 +8,11 @@ where
     R: Read + Seek,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "MemOverlay with {} layers and {} bytes of base content", self.patch_layers.len(), self.base_len)
+        if self.base_len_known {
+            write!(f, "MemOverlay with {} layers and {} bytes of base content", self.patch_layers.len(), self.base_len)
+        } else {
+            write!(f, "MemOverlay with {} layers and at least {} bytes of base content", self.patch_layers.len(), self.base_len)
+        }
     }
 }
 
@@ -0,0 +1,75 @@
+use std::{
+    cmp::min,
+    io::{Read, Result, Seek, Write},
+    sync::Arc,
+};
+
+use crate::{BaseEncoding, ChangeKind, MemOverlay};
+
+/// number of bytes which are encoded at once by [`MemOverlay::export`]
+const EXPORT_CHUNK_SIZE: u64 = 1024 * 1024;
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// declares that the base is stored using `encoding`. From now on, the
+    /// overlay shows the decoded bytes of the base, and all patches contain
+    /// decoded bytes. Existing patches are not changed. Observers are
+    /// informed that all bytes may have changed.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::{Cursor, Read};
+    /// use memoverlay::{MemOverlay, XorEncoding};
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new([0x48 ^ 0x20, 0x49 ^ 0x20]));
+    /// overlay.set_base_encoding(XorEncoding::new([0x20])).unwrap();
+    /// overlay.add_bytes_at(2, "!").unwrap();
+    /// assert_eq!(overlay.read_range(&(0..3)).unwrap(), b"HI!");
+    ///
+    /// let mut encoded = Vec::new();
+    /// overlay.export(&mut encoded).unwrap();
+    /// assert_eq!(encoded, [0x68, 0x69, 0x01]);
+    /// ```
+    pub fn set_base_encoding(&mut self, encoding: impl BaseEncoding + 'static) -> Result<()> {
+        self.replace_base_encoding(Some(Arc::new(encoding)))
+    }
+
+    /// shows the bytes of the base as they are stored
+    pub fn clear_base_encoding(&mut self) -> Result<()> {
+        self.replace_base_encoding(None)
+    }
+
+    fn replace_base_encoding(&mut self, encoding: Option<Arc<dyn BaseEncoding>>) -> Result<()> {
+        let pending = self.begin_change(0..self.data_len())?;
+        self.base_encoding = encoding;
+        let metadata = self.current_metadata();
+        self.finish_change(pending, ChangeKind::Encoding, metadata.as_ref())
+    }
+
+    pub fn base_encoding(&self) -> Option<&dyn BaseEncoding> {
+        self.base_encoding.as_deref()
+    }
+
+    /// writes all visible bytes to `writer`, after encoding them with the
+    /// encoding of the base, if there is one. This creates the data as it
+    /// would be stored, including all patches. Returns the number of bytes
+    /// which have been written.
+    pub fn export<W: Write>(&mut self, writer: &mut W) -> Result<u64> {
+        let data_len = self.data_len();
+        let mut offset = 0;
+        while offset < data_len {
+            let mut chunk = self.read_range(&(offset..min(data_len, offset + EXPORT_CHUNK_SIZE)))?;
+            if chunk.is_empty() {
+                break;
+            }
+            if let Some(encoding) = &self.base_encoding {
+                encoding.encode(offset, &mut chunk);
+            }
+            writer.write_all(&chunk)?;
+            offset += chunk.len() as u64;
+        }
+        Ok(offset)
+    }
+}
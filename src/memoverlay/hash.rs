@@ -0,0 +1,123 @@
+use std::io::{self, Read, Result, Seek, SeekFrom};
+
+use sha2::{Digest, Sha256};
+
+use crate::{merkle_tree::leaf_hash, MemOverlay, MerkleTree, OverlayError};
+
+/// number of bytes which are hashed at once
+const HASH_CHUNK_SIZE: u64 = 1024 * 1024;
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// computes the SHA-256 hash of all visible bytes. This is not
+    /// incremental: every call reads and hashes all of the data again. Use
+    /// [`MemOverlay::merkle_tree`] to hash only the blocks which have changed.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    /// overlay.add_bytes_at(7, "peter").unwrap();
+    ///
+    /// let mut expected = MemOverlay::from(Cursor::new(b"hello, peter!"));
+    /// assert_eq!(overlay.hash_view().unwrap(), expected.hash_view().unwrap());
+    /// ```
+    pub fn hash_view(&mut self) -> Result<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        let mut offset = 0;
+        while offset < self.data_len() {
+            let chunk = self.read_range(&(offset..offset + HASH_CHUNK_SIZE))?;
+            if chunk.is_empty() {
+                break;
+            }
+            hasher.update(&chunk);
+            offset += chunk.len() as u64;
+        }
+        Ok(hasher.finalize().into())
+    }
+
+    /// computes the length and the SHA-256 hash of the base as it is stored,
+    /// without any patches and without decoding it
+    pub(crate) fn hash_base(&mut self) -> Result<(u64, [u8; 32])> {
+        let len = self.base.seek(SeekFrom::End(0))?;
+        self.base.seek(SeekFrom::Start(0))?;
+        let mut hasher = Sha256::new();
+        let result = io::copy(&mut (&mut self.base).take(len), &mut hasher);
+        self.base.seek(SeekFrom::Start(self.pos))?;
+        if result? != len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the base is too short"));
+        }
+        Ok((len, hasher.finalize().into()))
+    }
+
+    /// starts maintaining a [`MerkleTree`] over blocks of `block_size` bytes.
+    /// Afterwards, [`MemOverlay::merkle_tree`] hashes only the blocks which
+    /// have been changed by writes, reverts, aborts and changes of the base
+    /// encoding since its last invocation.
+    ///
+    /// Changes which happen outside of the overlay are not noticed, except
+    /// for changes of the length: a base which is modified, including the
+    /// bytes shown by snapshot copies, or the source of a patch added by
+    /// [`MemOverlay::add_external_at`] which changes. Such changes must be
+    /// reported with [`MemOverlay::invalidate`].
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(vec![0; 4096]));
+    /// overlay.enable_merkle_tree(1024).unwrap();
+    /// let before = overlay.merkle_tree().unwrap().unwrap().root();
+    ///
+    /// // only the second block is hashed again
+    /// overlay.add_bytes_at(1500, "peter").unwrap();
+    /// let tree = overlay.merkle_tree().unwrap().unwrap();
+    /// assert_eq!(tree.block_count(), 4);
+    /// assert_ne!(tree.root(), before);
+    /// ```
+    pub fn enable_merkle_tree(&mut self, block_size: u64) -> std::result::Result<(), OverlayError> {
+        if block_size == 0 {
+            return Err(OverlayError::InvalidBlockSize(block_size));
+        }
+        self.merkle_tree = Some(MerkleTree::new(block_size));
+        Ok(())
+    }
+
+    pub fn disable_merkle_tree(&mut self) {
+        self.merkle_tree = None;
+    }
+
+    /// hashes all blocks which have changed and returns the updated tree, or
+    /// `None` if [`MemOverlay::enable_merkle_tree`] has not been called
+    pub fn merkle_tree(&mut self) -> Result<Option<&MerkleTree>> {
+        let Some(mut tree) = self.merkle_tree.take() else {
+            return Ok(None);
+        };
+
+        let data_len = self.data_len();
+        let mut hashes = Vec::new();
+        let mut result = Ok(());
+        for index in tree.dirty_blocks(data_len) {
+            let begin = index as u64 * tree.block_size();
+            let end = (begin + tree.block_size()).min(data_len);
+            match self.read_range(&(begin..end)) {
+                Ok(block) => hashes.push((index, leaf_hash(&block))),
+                Err(why) => {
+                    result = Err(why);
+                    break;
+                }
+            }
+        }
+        if result.is_ok() {
+            tree.update(data_len, hashes);
+        }
+
+        self.merkle_tree = Some(tree);
+        result.map(|_| self.merkle_tree.as_ref())
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    cmp::min,
+    io::{Read, Result, Seek, SeekFrom},
+};
+
+use crate::{BaseEncoding, OverlayError, Patch, PatchContent, PatchLayer, PatchSearchResult};
+
+/// maximum number of references which are followed to read a single byte.
+/// This limit is only reached by cyclic live copies.
+pub(super) const MAX_REFERENCE_DEPTH: usize = 32;
+
+/// gives access to the decoded bytes of the base
+pub(crate) struct BaseReader<'a, R> {
+    pub(crate) reader: &'a mut R,
+    pub(crate) len: u64,
+    pub(crate) encoding: Option<&'a dyn BaseEncoding>,
+}
+
+impl<R: Read + Seek> BaseReader<'_, R> {
+    /// reads the decoded bytes at `pos`, but not beyond the end of the base
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> Result<usize> {
+        let length = min(buf.len() as u64, self.len.saturating_sub(pos)) as usize;
+        self.reader.seek(SeekFrom::Start(pos))?;
+        let bytes = self.reader.read(&mut buf[..length])?;
+        if let Some(encoding) = self.encoding {
+            encoding.decode(pos, &mut buf[..bytes]);
+        }
+        Ok(bytes)
+    }
+}
+
+/// reads from `offset` of the data which consists of `layers` above `base`
+pub(crate) fn read_layers<R: Read + Seek>(
+    layers: &[PatchLayer],
+    base: &mut BaseReader<'_, R>,
+    offset: u64,
+    buf: &mut [u8],
+    depth: usize,
+) -> Result<usize> {
+    let mut bytes_read = 0;
+    while bytes_read < buf.len() {
+        let pos = offset + bytes_read as u64;
+        let (current, next_begin) = locate(layers, pos);
+        let length = match next_begin {
+            Some(next_begin) => min(buf.len() - bytes_read, (next_begin - pos) as usize),
+            None => buf.len() - bytes_read,
+        };
+        let chunk = &mut buf[bytes_read..bytes_read + length];
+
+        let bytes = match current {
+            Some((index, patch)) => {
+                read_patch(layers, index, base, patch, pos - patch.begin(), chunk, depth)?
+            }
+            None if pos < base.len => base.read_at(pos, chunk)?,
+            None if next_begin.is_some() => {
+                // the gap between the end of the base and the next patch
+                chunk.fill(0);
+                length
+            }
+            None => 0,
+        };
+        if bytes == 0 {
+            break;
+        }
+        bytes_read += bytes;
+    }
+    Ok(bytes_read)
+}
+
+/// reads the bytes of `patch`, which is part of `layers[index]`, starting
+/// `offset` bytes after its beginning. References are resolved using `layers`,
+/// unless they have their own snapshot.
+pub(crate) fn read_patch<R: Read + Seek>(
+    layers: &[PatchLayer],
+    index: usize,
+    base: &mut BaseReader<'_, R>,
+    patch: &Patch,
+    offset: u64,
+    buf: &mut [u8],
+    depth: usize,
+) -> Result<usize> {
+    match patch.content() {
+        PatchContent::Reference {
+            source,
+            len,
+            snapshot,
+        } => {
+            if depth >= MAX_REFERENCE_DEPTH {
+                return Err(OverlayError::ReferenceDepthExceeded(MAX_REFERENCE_DEPTH).into());
+            }
+            let length = min(buf.len() as u64, len.saturating_sub(offset)) as usize;
+            match snapshot {
+                None => read_layers(layers, base, source + offset, &mut buf[..length], depth + 1),
+                Some(snapshot) => {
+                    // the base as it was when the snapshot has been created
+                    let mut base = BaseReader {
+                        reader: &mut *base.reader,
+                        len: snapshot.base_len,
+                        encoding: snapshot.base_encoding.as_deref(),
+                    };
+                    read_layers(&snapshot.layers, &mut base, source + offset, &mut buf[..length], depth + 1)
+                }
+            }
+        }
+        PatchContent::Masked { value, mask } => {
+            let offset = offset as usize;
+            let length = min(buf.len(), value.len().saturating_sub(offset));
+            let buf = &mut buf[..length];
+
+            // bytes beyond the end of the data beneath are zero
+            let below = read_layers(&layers[index + 1..], base, patch.begin() + offset as u64, buf, depth)?;
+            buf[below..].fill(0);
+
+            for (byte, (value, mask)) in buf
+                .iter_mut()
+                .zip(value[offset..].iter().zip(mask[offset..].iter()))
+            {
+                *byte = (*byte & !mask) | (value & mask);
+            }
+            Ok(length)
+        }
+        PatchContent::Computed {
+            transform, input, ..
+        } => {
+            let length = min(buf.len() as u64, patch.len().saturating_sub(offset)) as usize;
+            let input_len: usize = (input.end - input.start)
+                .try_into()
+                .map_err(std::io::Error::other)?;
+
+            // bytes beyond the end of the data beneath are zero
+            let mut bytes = vec![0; input_len];
+            read_layers(&layers[index + 1..], base, input.start, &mut bytes, depth)?;
+            transform.transform(&mut bytes);
+
+            let from = (patch.begin() + offset - input.start) as usize;
+            buf[..length].copy_from_slice(&bytes[from..from + length]);
+            Ok(length)
+        }
+        _ => patch.read(offset, buf),
+    }
+}
+
+/// finds the topmost patch which contains `pos` together with the index of
+/// its layer, and the beginning of the
+/// next patch behind `pos` in any layer
+pub(crate) fn locate(layers: &[PatchLayer], pos: u64) -> (Option<(usize, &Patch)>, Option<u64>) {
+    let mut current = None;
+    let mut next_begin: Option<u64> = None;
+    for (index, layer) in layers.iter().enumerate() {
+        let (current_patch, next_patch) = match layer.next_patch_for(pos) {
+            PatchSearchResult::PatchFollows { next_patch } => (None, Some(next_patch)),
+            PatchSearchResult::CurrentPatchIsTheLastOne { current_patch } => (Some(current_patch), None),
+            PatchSearchResult::CurrentPatchIsFollowedBy {
+                current_patch,
+                next_patch,
+            } => (Some(current_patch), Some(next_patch)),
+            PatchSearchResult::NoMorePatches => (None, None),
+        };
+        if current.is_none() {
+            current = current_patch.map(|patch| (index, patch));
+        }
+        if let Some(next_patch) = next_patch {
+            next_begin = Some(next_begin.map_or(next_patch.begin(), |begin| min(begin, next_patch.begin())));
+        }
+    }
+    (current, next_begin)
+}
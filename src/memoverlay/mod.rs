@@ -1,14 +1,31 @@
 use std::{
     cmp::min,
-    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom},
+    ops::Range,
+    sync::Arc,
 };
 
+mod blame;
+mod copy;
 mod display;
+mod encoding;
+#[cfg(feature = "hash")]
+mod hash;
+mod layers;
+mod modified;
+mod observe;
+mod protect;
 mod read;
+mod revert;
+mod search;
 mod seek;
+mod strings;
+mod tracking;
+mod transaction;
+mod typed;
 mod write;
 
-use crate::{Patch, PatchLayer};
+use crate::{BaseEncoding, ByteTransform, ChangeTracker, Observer, ObserverId, Patch, PatchLayer, PatchMetadata, RangeSet, SharedSource, WritePolicy};
 
 /// Puts a writable layer of bytes over some byte stream
 ///
@@ -29,8 +46,24 @@ pub struct MemOverlay<R: Read + Seek> {
     #[allow(dead_code)]
     base: R,
     base_len: u64,
+    base_len_known: bool,
+    auto_refresh_base_len: bool,
     pos: u64,
     patch_layers: Vec<PatchLayer>,
+    write_policy: WritePolicy,
+    protected_ranges: RangeSet,
+    observers: Vec<(ObserverId, Observer)>,
+    next_observer_id: usize,
+    transaction: Option<Box<transaction::TransactionState>>,
+    base_encoding: Option<Arc<dyn BaseEncoding>>,
+    metadata: Option<PatchMetadata>,
+    change_tracker: Option<ChangeTracker>,
+    #[cfg(feature = "hash")]
+    merkle_tree: Option<crate::MerkleTree>,
+
+    /// if set, changes whose bytes would be omitted from their event are
+    /// reported in parts which contain all of their bytes
+    complete_events: bool,
 }
 
 impl<R> From<R> for MemOverlay<R>
@@ -48,8 +81,21 @@ where
         Self {
             base,
             base_len,
+            base_len_known: true,
+            auto_refresh_base_len: false,
             pos,
             patch_layers: Default::default(),
+            write_policy: Default::default(),
+            protected_ranges: Default::default(),
+            observers: Default::default(),
+            next_observer_id: 0,
+            transaction: None,
+            base_encoding: None,
+            metadata: None,
+            change_tracker: None,
+            #[cfg(feature = "hash")]
+            merkle_tree: None,
+            complete_events: false,
         }
     }
 }
@@ -58,12 +104,219 @@ impl<R> MemOverlay<R>
 where
     R: Read + Seek,
 {
+    /// creates an overlay over a base whose length is not known in advance,
+    /// such as a [`crate::StreamBase`]. The length of the base is updated
+    /// while reading, and is only determined completely when the end of the
+    /// base is reached or when seeking beyond the bytes seen so far
+    pub fn with_unknown_len(base: R) -> Self {
+        let mut base = base;
+        let pos = base.stream_position().unwrap();
+
+        Self {
+            base,
+            base_len: pos,
+            base_len_known: false,
+            auto_refresh_base_len: false,
+            pos,
+            patch_layers: Default::default(),
+            write_policy: Default::default(),
+            protected_ranges: Default::default(),
+            observers: Default::default(),
+            next_observer_id: 0,
+            transaction: None,
+            base_encoding: None,
+            metadata: None,
+            change_tracker: None,
+            #[cfg(feature = "hash")]
+            merkle_tree: None,
+            complete_events: false,
+        }
+    }
+
+    /// returns the length of the base, or `None` if it has not been
+    /// determined yet
+    pub fn base_len(&self) -> Option<u64> {
+        if self.base_len_known {
+            Some(self.base_len)
+        } else {
+            None
+        }
+    }
+
+    /// determines the length of the base by seeking to its end. Use this if
+    /// the base can grow, e.g. if it is a file which is still being written.
+    /// Patches which have been placed behind the previous end of the base
+    /// still take precedence over the new base data.
+    pub fn refresh_base_len(&mut self) -> Result<u64> {
+        self.base_len = self.base.seek(SeekFrom::End(0))?;
+        self.base_len_known = true;
+        self.base.seek(SeekFrom::Start(self.pos))?;
+        Ok(self.base_len)
+    }
+
+    /// makes sure that the base covers the position `target` if the base is
+    /// that long. If the length of the base is not known yet, only the base
+    /// up to `target` is read instead of all of it
+    fn refresh_base_len_to(&mut self, target: u64) -> Result<u64> {
+        if self.base_len_known || target < self.base_len {
+            return self.refresh_base_len();
+        }
+
+        self.base.seek(SeekFrom::Start(target))?;
+        let mut byte = [0];
+        if self.base.read(&mut byte)? == 0 {
+            return self.refresh_base_len();
+        }
+        self.base_len = target.saturating_add(1);
+        self.base.seek(SeekFrom::Start(self.pos))?;
+        Ok(self.base_len)
+    }
+
+    /// if enabled, the length of the base is checked again whenever a read
+    /// reaches the end of the base or a seek goes beyond the last valid
+    /// position
+    pub fn set_auto_refresh_base_len(&mut self, enabled: bool) {
+        self.auto_refresh_base_len = enabled;
+    }
+
+    pub fn auto_refresh_base_len(&self) -> bool {
+        self.auto_refresh_base_len
+    }
+
+    fn base_len_is_stale(&self) -> bool {
+        !self.base_len_known || self.auto_refresh_base_len
+    }
+
     pub fn add_bytes_at(&mut self, offset: u64, bytes: impl AsRef<[u8]>) -> Result<usize> {
-        let current_position = self.stream_position()?;
-        self.seek(SeekFrom::Start(offset))?;
-        let bytes = self.write(bytes.as_ref())?;
-        self.seek(SeekFrom::Start(current_position))?;
-        Ok(bytes)
+        self.write_at(offset, bytes.as_ref())
+    }
+
+    /// fills `len` bytes at `offset` by repeating `pattern`, see
+    /// [`Patch::fill`]. This needs only the memory for `pattern`, regardless
+    /// of `len`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(vec![0xffu8; 1 << 20]));
+    /// overlay.fill_at(16, [0], (1 << 20) - 32).unwrap();
+    /// assert_eq!(overlay.read_u64_le_at(1 << 19).unwrap(), 0);
+    /// assert_eq!(overlay.read_u8_at((1 << 20) - 1).unwrap(), 0xff);
+    /// ```
+    pub fn fill_at(&mut self, offset: u64, pattern: impl AsRef<[u8]>, len: u64) -> Result<()> {
+        let patch = Patch::fill(offset, pattern, len)?;
+        self.add_patch(patch)
+    }
+
+    /// changes only those bits at `offset` which are set in `mask`, see
+    /// [`Patch::masked`]. The other bits keep following the bytes beneath
+    /// the patch, even if these are changed later.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new([0x0fu8; 4]));
+    /// overlay.write_masked_at(1, [0x80], [0x81]).unwrap();
+    /// assert_eq!(overlay.read_u8_at(1).unwrap(), 0x8e);
+    /// ```
+    pub fn write_masked_at(&mut self, offset: u64, value: impl AsRef<[u8]>, mask: impl AsRef<[u8]>) -> Result<()> {
+        let patch = Patch::masked(offset, value, mask)?;
+        self.add_patch(patch)
+    }
+
+    /// replaces the `len` bytes at `offset` by the result of `transform`,
+    /// which is applied to the bytes beneath the patch whenever they are
+    /// read, see [`Patch::computed`]
+    pub fn transform_at(&mut self, offset: u64, len: u64, transform: impl ByteTransform + 'static) -> Result<()> {
+        let patch = Patch::computed(offset, len, transform)?;
+        self.add_patch(patch)
+    }
+
+    /// places the bytes in `range` of `source` at `offset`, without copying
+    /// them, see [`Patch::external`]
+    pub fn add_external_at(&mut self, offset: u64, source: SharedSource, range: Range<u64>) -> Result<()> {
+        let patch = Patch::external(offset, source, range)?;
+        self.add_patch(patch)
+    }
+
+    /// reads from `offset` without changing the current position. Like
+    /// [`Read::read`], this reads as many bytes as are available
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let current_position = self.pos;
+        self.base.seek(SeekFrom::Start(offset))?;
+        self.pos = offset;
+
+        let result = self.read(buf);
+
+        self.base.seek(SeekFrom::Start(current_position))?;
+        self.pos = current_position;
+        result
+    }
+
+    /// returns the visible bytes in `range`. The result is shorter than
+    /// `range` if the range reaches beyond the end of the data
+    pub fn read_range(&mut self, range: &Range<u64>) -> Result<Vec<u8>> {
+        let length: usize = range
+            .end
+            .saturating_sub(range.start)
+            .try_into()
+            .map_err(Error::other)?;
+        let mut buf = vec![0; length];
+        let bytes = self.read_at(range.start, &mut buf)?;
+        buf.truncate(bytes);
+        Ok(buf)
+    }
+
+    pub fn write_policy(&self) -> &WritePolicy {
+        &self.write_policy
+    }
+
+    /// sets the policy which is used to check all following writes. Existing
+    /// patches are not affected.
+    pub fn set_write_policy(&mut self, write_policy: WritePolicy) {
+        self.write_policy = write_policy;
+    }
+
+    /// returns the number of bytes which are stored in patches
+    pub fn patch_bytes(&self) -> u64 {
+        self.patch_layers
+            .iter()
+            .flat_map(|layer| layer.iter_patches())
+            .map(|patch| patch.end() - patch.begin())
+            .sum()
+    }
+
+    /// splits all patches into non-overlapping segments, which contain the
+    /// range of visible bytes, the index of the layer and the patch which
+    /// supplies these bytes. The segments are ordered by their offset.
+    pub(crate) fn effective_segments(&self) -> Vec<(Range<u64>, usize, &Patch)> {
+        let mut segments = Vec::new();
+        let mut covered = RangeSet::default();
+        for (index, layer) in self.patch_layers.iter().enumerate() {
+            for patch in layer.iter_patches() {
+                for gap in covered.gaps(&(patch.begin()..patch.end())) {
+                    segments.push((gap, index, patch));
+                }
+            }
+            for patch in layer.iter_patches() {
+                covered.insert(patch.begin()..patch.end());
+            }
+        }
+        segments.sort_by_key(|(range, _, _)| range.start);
+        segments
+    }
+
+    /// returns the number of bytes which can be read, including the base and
+    /// all patches
+    pub fn data_len(&self) -> u64 {
+        std::cmp::max(
+            self.base_len,
+            self.last_overlay_position().map_or(0, |pos| pos + 1),
+        )
     }
 
     pub fn last_overlay_position(&self) -> Option<u64> {
@@ -83,7 +336,7 @@ where
     }
 
     pub fn last_base_position(&self) -> u64 {
-        self.base_len - 1
+        self.base_len.saturating_sub(1)
     }
 
     pub fn last_valid_position(&self) -> u64 {
@@ -95,6 +348,9 @@ where
 
     fn set_new_position(&mut self, new_pos: u64) -> Result<u64> {
         //println!("set new position to {new_pos}");
+        if new_pos > self.last_valid_position() && self.base_len_is_stale() {
+            self.refresh_base_len_to(new_pos)?;
+        }
         if new_pos > self.last_valid_position() {
             Err(Error::new(
                 ErrorKind::UnexpectedEof,
@@ -111,8 +367,7 @@ where
         self.pos += match TryInto::<u64>::try_into(bytes) {
             Ok(bytes) => bytes,
             Err(_why) => {
-                return Err(Error::new(
-                    ErrorKind::Other,
+                return Err(Error::other(
                     "read more bytes than can be displayed with a 64 bit counter",
                 ))
             }
@@ -123,93 +378,60 @@ where
     /// read the next chunk of data. This might be a part of a patch, or data
     /// from the base stream
     fn read_next_chunk(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let next_patches: Vec<_> = self
-            .patch_layers
-            .iter()
-            .map(|layer| layer.next_patch_for(self.pos))
-            .collect();
-
-        let mut current: Option<&Patch> = None;
-        let mut next: Option<&Patch> = None;
-        for result in next_patches {
-            match result {
-                crate::PatchSearchResult::PatchFollows { next_patch } => {
-                    if let Some(p) = next {
-                        if next_patch.begin() < p.begin() {
-                            next = Some(next_patch)
-                        }
-                    } else {
-                        next = Some(next_patch)
-                    }
-                }
-                crate::PatchSearchResult::CurrentPatchIsTheLastOne { current_patch } => {
-                    // we are inside a patch, so we MUST read from it
-                    if current.is_none() {
-                        current = Some(current_patch);
-                    }
-                }
-                crate::PatchSearchResult::CurrentPatchIsFollowedBy {
-                    current_patch,
-                    next_patch,
-                } => {
-                    // we are inside a patch, so we MUST read from it
-                    if current.is_none() {
-                        current = Some(current_patch);
-                    }
-
-                    // but we must also take a look at where the next patch is
-                    if let Some(p) = next {
-                        if next_patch.begin() < p.begin() {
-                            next = Some(next_patch)
-                        }
-                    } else {
-                        next = Some(next_patch)
-                    }
-                }
-                _ => (),
-            }
-        }
+        let (current, next_begin) = layers::locate(&self.patch_layers, self.pos);
+        let length = match next_begin {
+            Some(next_begin) => min(buf.len(), (next_begin - self.pos).try_into().unwrap()),
+            None => buf.len(),
+        };
 
         match current {
-            Some(current_patch) => {
-                let bytes = self.read_from_patch(current_patch, next, buf)?;
+            Some((index, current_patch)) => {
+                let offset = self.pos - current_patch.begin();
+                let mut base = layers::BaseReader {
+                    reader: &mut self.base,
+                    len: self.base_len,
+                    encoding: self.base_encoding.as_deref(),
+                };
+                let bytes = layers::read_patch(
+                    &self.patch_layers,
+                    index,
+                    &mut base,
+                    current_patch,
+                    offset,
+                    &mut buf[0..length],
+                    0,
+                )?;
                 self.shift_position(bytes)?;
-                self.base
-                    .seek(SeekFrom::Current(bytes.try_into().unwrap()))?;
+                self.base.seek(SeekFrom::Start(self.pos))?;
                 Ok(bytes)
             }
             None => {
-                #[allow(clippy::unused_io_amount)]
-                let bytes = match next {
-                    Some(next_patch) => {
-                        let length: usize = min(
-                            buf.len(),
-                            (next_patch.begin() - self.pos).try_into().unwrap(),
-                        );
-                        self.base.read(&mut buf[0..length])?
+                let mut bytes = self.base.read(&mut buf[0..length])?;
+                if bytes == 0 && length > 0 && self.base_len_is_stale() {
+                    // we reached the end of the base, but the base might
+                    // have grown in the meantime
+                    if self.refresh_base_len()? > self.pos {
+                        bytes = self.base.read(&mut buf[0..length])?;
                     }
-                    None => self.base.read(buf)?,
-                };
+                }
+
+                if bytes == 0 && next_begin.is_some() {
+                    // there is a gap between the end of the base and the
+                    // next patch, which we fill with zeros
+                    buf[0..length].fill(0);
+                    bytes = length;
+                    self.base.seek(SeekFrom::Start(self.pos + bytes as u64))?;
+                    self.shift_position(bytes)?;
+                    return Ok(bytes);
+                }
+
+                if let Some(encoding) = &self.base_encoding {
+                    encoding.decode(self.pos, &mut buf[0..bytes]);
+                }
+                self.base_len = std::cmp::max(self.base_len, self.pos + bytes as u64);
                 self.shift_position(bytes)?;
                 Ok(bytes)
             }
         }
     }
-
-    fn read_from_patch(
-        &self,
-        current_patch: &Patch,
-        next_patch: Option<&Patch>,
-        buf: &mut [u8],
-    ) -> Result<usize> {
-        let offset = self.pos - current_patch.begin();
-        let bytes = match next_patch {
-            Some(next_patch) => {
-                let length: usize = min(buf.len(), (next_patch.begin() - self.pos).try_into().unwrap());
-                current_patch.read(offset, &mut buf[0..length])?
-            }
-            None => current_patch.read(offset, buf)?,
-        };
-        Ok(bytes)
-    }
 }
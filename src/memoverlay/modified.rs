@@ -0,0 +1,70 @@
+use std::{
+    io::{Read, Result, Seek},
+    ops::Range,
+};
+
+use crate::MemOverlay;
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// returns the visible bytes of all patches, as non-overlapping ranges
+    /// in ascending order. Parts of patches which are hidden by newer patches
+    /// are left out, and adjacent ranges of different patches are not
+    /// merged. The bytes are read only when the iterator reaches them.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    /// overlay.add_bytes_at(3, "XXXX").unwrap();
+    /// overlay.add_bytes_at(5, "YY").unwrap();
+    /// overlay.add_bytes_at(12, "?").unwrap();
+    ///
+    /// let ranges: Vec<_> = overlay.modified_ranges().collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(
+    ///     ranges,
+    ///     vec![
+    ///         (3..5, b"XX".to_vec()),
+    ///         (5..7, b"YY".to_vec()),
+    ///         (12..13, b"?".to_vec()),
+    ///     ]
+    /// );
+    /// assert!(overlay.is_modified(0..4));
+    /// assert!(!overlay.is_modified(7..12));
+    /// assert_eq!(overlay.modified_len(), 5);
+    /// ```
+    pub fn modified_ranges(&mut self) -> impl Iterator<Item = Result<(Range<u64>, Vec<u8>)>> + '_ {
+        let ranges: Vec<_> = self
+            .effective_segments()
+            .into_iter()
+            .map(|(range, _, _)| range)
+            .collect();
+        ranges.into_iter().map(move |range| {
+            let bytes = self.read_range(&range)?;
+            Ok((range, bytes))
+        })
+    }
+
+    /// returns `true` if any byte in `range` is supplied by a patch. Bytes
+    /// which have been written with their original value count as modified.
+    pub fn is_modified(&self, range: Range<u64>) -> bool {
+        !range.is_empty()
+            && self.patch_layers.iter().any(|layer| {
+                layer
+                    .iter_patches()
+                    .any(|patch| patch.begin() < range.end && range.start < patch.end())
+            })
+    }
+
+    /// returns the number of visible bytes which are supplied by patches
+    pub fn modified_len(&self) -> u64 {
+        self.effective_segments()
+            .iter()
+            .map(|(range, _, _)| range.end - range.start)
+            .sum()
+    }
+}
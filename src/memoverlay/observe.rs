@@ -0,0 +1,269 @@
+use std::{
+    cmp::{max, min},
+    io::{Read, Result, Seek},
+    ops::Range,
+    sync::Arc,
+};
+
+use crate::{
+    ChangeBytes, ChangeEvent, ChangeKind, MemOverlay, ObserverId, PatchContent, PatchMetadata, RangeSet,
+    MAX_EVENT_BYTES,
+};
+
+use super::layers::MAX_REFERENCE_DEPTH;
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// registers a callback which is invoked after every write, revert or
+    /// compaction. The callback receives the affected range together with
+    /// the bytes which were visible before and after the change. Bytes which
+    /// change because they are shown by a live copy are reported by
+    /// additional events, see [`crate::CopyMode::Live`]. The bytes of ranges
+    /// which are longer than [`MAX_EVENT_BYTES`] are not copied, see
+    /// [`ChangeBytes`].
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use std::sync::{Arc, Mutex};
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let events = Arc::new(Mutex::new(Vec::new()));
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    /// let recorder = Arc::clone(&events);
+    /// overlay.add_observer(move |event| recorder.lock().unwrap().push(event.clone()));
+    ///
+    /// overlay.add_bytes_at(7, "peter").unwrap();
+    ///
+    /// let events = events.lock().unwrap();
+    /// assert_eq!(events[0].range, 7..12);
+    /// assert_eq!(events[0].old_bytes, b"world");
+    /// assert_eq!(events[0].new_bytes, b"peter");
+    /// ```
+    pub fn add_observer(&mut self, observer: impl Fn(&ChangeEvent) + Send + Sync + 'static) -> ObserverId {
+        let id = ObserverId(self.next_observer_id);
+        self.next_observer_id += 1;
+        self.observers.push((id, Arc::new(observer)));
+        id
+    }
+
+    /// unregisters an observer. Returns `false` if there was no such observer
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        let count = self.observers.len();
+        self.observers.retain(|(observer_id, _)| *observer_id != id);
+        count != self.observers.len()
+    }
+
+    /// reads the currently visible bytes in `range`, but only if there is
+    /// someone who is interested in them
+    pub(crate) fn observed_bytes(&mut self, range: &Range<u64>) -> Result<Option<ChangeBytes>> {
+        if self.observers.is_empty() {
+            Ok(None)
+        } else {
+            self.event_bytes(range).map(Some)
+        }
+    }
+
+    /// returns the currently visible bytes in `range`, or a description of
+    /// them if there are more than [`MAX_EVENT_BYTES`]
+    pub(crate) fn event_bytes(&mut self, range: &Range<u64>) -> Result<ChangeBytes> {
+        if range.end.saturating_sub(range.start) <= MAX_EVENT_BYTES {
+            return self.read_range(range).map(ChangeBytes::Bytes);
+        }
+        Ok(match self.fill_pattern(range) {
+            Some(pattern) => ChangeBytes::Fill { pattern },
+            None => ChangeBytes::Omitted,
+        })
+    }
+
+    /// returns the first part of `range` together with its bytes, such that
+    /// no bytes are omitted: all of `range` if its bytes can be part of an
+    /// event, otherwise the next [`MAX_EVENT_BYTES`] bytes. The part beyond
+    /// the end of the data has no bytes.
+    pub(crate) fn complete_chunk(&mut self, range: &Range<u64>) -> Result<(Range<u64>, ChangeBytes)> {
+        match self.event_bytes(range)? {
+            ChangeBytes::Omitted if range.start >= self.data_len() => {
+                Ok((range.clone(), ChangeBytes::Bytes(Vec::new())))
+            }
+            ChangeBytes::Omitted => {
+                let chunk = range.start..min(range.end, range.start + MAX_EVENT_BYTES);
+                let bytes = self.read_range(&chunk)?;
+                Ok((chunk, ChangeBytes::Bytes(bytes)))
+            }
+            bytes => Ok((range.clone(), bytes)),
+        }
+    }
+
+    /// requests that changes are reported with all of their bytes, in parts
+    /// of at most [`MAX_EVENT_BYTES`] bytes, see [`MemOverlay::complete_chunk`]
+    #[cfg(feature = "audit")]
+    pub(crate) fn set_complete_events(&mut self, enabled: bool) {
+        self.complete_events = enabled;
+    }
+
+    /// returns the pattern which is repeated in `range`, starting with its
+    /// first byte, if all of `range` is shown by a single fill patch
+    pub(crate) fn fill_pattern(&self, range: &Range<u64>) -> Option<Vec<u8>> {
+        for layer in self.patch_layers.iter() {
+            let mut overlapping = layer
+                .iter_patches()
+                .filter(|patch| patch.begin() < range.end && range.start < patch.end());
+            if let Some(patch) = overlapping.next() {
+                if patch.begin() > range.start || patch.end() < range.end {
+                    return None;
+                }
+                return match patch.slice(range.clone())?.content() {
+                    PatchContent::Fill { pattern, .. } => Some(pattern.clone()),
+                    _ => None,
+                };
+            }
+        }
+        None
+    }
+
+    /// informs all observers about a change of the bytes in `range`
+    pub(crate) fn notify(
+        &mut self,
+        kind: ChangeKind,
+        range: Range<u64>,
+        old_bytes: Option<ChangeBytes>,
+        metadata: Option<&PatchMetadata>,
+    ) -> Result<()> {
+        if let Some(old_bytes) = old_bytes {
+            let new_bytes = self.event_bytes(&range)?;
+            if !self.complete_events || !matches!(new_bytes, ChangeBytes::Omitted) {
+                self.emit(ChangeEvent {
+                    kind,
+                    range,
+                    old_bytes,
+                    new_bytes,
+                    metadata: metadata.cloned(),
+                });
+                return Ok(());
+            }
+
+            // the old bytes have been read before the change, so they cannot
+            // be split anymore
+            let mut rest = range;
+            while !rest.is_empty() {
+                let (range, new_bytes) = self.complete_chunk(&rest)?;
+                rest.start = range.end;
+                self.emit(ChangeEvent {
+                    kind,
+                    range,
+                    old_bytes: ChangeBytes::Omitted,
+                    new_bytes,
+                    metadata: metadata.cloned(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, event: ChangeEvent) {
+        match &mut self.transaction {
+            Some(transaction) => transaction.pending_events.push(event),
+            None => self.dispatch(&event),
+        }
+    }
+
+    pub(crate) fn dispatch(&self, event: &ChangeEvent) {
+        for (_, observer) in self.observers.iter() {
+            observer(event);
+        }
+    }
+
+    /// collects the ranges whose visible bytes change if the bytes in `range`
+    /// change: `range` itself, followed by the live copies of its bytes.
+    /// Their current bytes are kept for the observers. This must be called
+    /// before the change, and be followed by [`MemOverlay::finish_change`].
+    pub(crate) fn begin_change(&mut self, range: Range<u64>) -> Result<PendingChange> {
+        let mut ranges = vec![range.clone()];
+        if !self.observers.is_empty() || self.tracks_changes() {
+            let mut copies = self.with_live_copies(range.clone());
+            copies.remove(range);
+            ranges.extend(copies.iter());
+        }
+
+        let mut pending = PendingChange { ranges: Vec::new() };
+        for range in ranges {
+            let old_bytes = self.observed_bytes(&range)?;
+            pending.ranges.push((range, old_bytes));
+        }
+        Ok(pending)
+    }
+
+    /// informs everyone who is interested in the changed ranges
+    pub(crate) fn finish_change(
+        &mut self,
+        pending: PendingChange,
+        kind: ChangeKind,
+        metadata: Option<&PatchMetadata>,
+    ) -> Result<()> {
+        for (range, old_bytes) in pending.ranges {
+            self.mark_changed(range.clone());
+            self.notify(kind, range, old_bytes, metadata)?;
+        }
+        Ok(())
+    }
+
+    /// records that the visible bytes in `range` may have changed, for
+    /// everything which tracks the changed parts of the data
+    pub(crate) fn mark_changed(&mut self, range: Range<u64>) {
+        if let Some(transaction) = &mut self.transaction {
+            transaction.changed.insert(range.clone());
+        }
+        if let Some(tracker) = &mut self.change_tracker {
+            tracker.changed.insert(range.clone());
+        }
+        #[cfg(feature = "hash")]
+        if let Some(tree) = &mut self.merkle_tree {
+            tree.mark_dirty(range);
+        }
+    }
+
+    fn tracks_changes(&self) -> bool {
+        #[cfg(feature = "hash")]
+        if self.merkle_tree.is_some() {
+            return true;
+        }
+        self.change_tracker.is_some()
+    }
+
+    /// returns `range` together with the ranges of all live copies which
+    /// show bytes of `range`, directly or through other live copies
+    pub(crate) fn with_live_copies(&self, range: Range<u64>) -> RangeSet {
+        let mut changed = RangeSet::default();
+        changed.insert(range);
+        for _ in 0..MAX_REFERENCE_DEPTH {
+            let mut grown = changed.clone();
+            for patch in self.patch_layers.iter().flat_map(|layer| layer.iter_patches()) {
+                if let PatchContent::Reference {
+                    source,
+                    len,
+                    snapshot: None,
+                } = patch.content()
+                {
+                    for part in changed.overlapping(&(*source..source + len)) {
+                        let begin = max(part.start, *source) - source + patch.begin();
+                        let end = min(part.end, source + len) - source + patch.begin();
+                        grown.insert(begin..end);
+                    }
+                }
+            }
+            if grown == changed {
+                break;
+            }
+            changed = grown;
+        }
+        changed
+    }
+}
+
+/// the ranges which are affected by a change, together with their bytes
+/// before the change, see [`MemOverlay::begin_change`]
+pub(crate) struct PendingChange {
+    ranges: Vec<(Range<u64>, Option<ChangeBytes>)>,
+}
@@ -0,0 +1,81 @@
+use std::{
+    io::{Read, Seek},
+    ops::Range,
+};
+
+use crate::{MemOverlay, OverlayError, Patch, ProtectionMode};
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// marks a range as read-only. Writes and reverts which touch this range
+    /// are handled according to [`crate::WritePolicy::protection_mode`].
+    /// Existing patches inside the range are kept.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new([0u8; 1024]));
+    /// overlay.protect(0x1be..0x200);
+    ///
+    /// assert!(overlay.is_protected(0x1fe));
+    /// assert!(overlay.add_bytes_at(0x1fe, [0x55, 0xaa]).is_err());
+    /// assert!(overlay.add_bytes_at(0x200, [0x55, 0xaa]).is_ok());
+    /// ```
+    pub fn protect(&mut self, range: Range<u64>) {
+        self.protected_ranges.insert(range)
+    }
+
+    /// removes the protection from all bytes in `range`
+    pub fn unprotect(&mut self, range: Range<u64>) {
+        self.protected_ranges.remove(range)
+    }
+
+    pub fn is_protected(&self, offset: u64) -> bool {
+        self.protected_ranges.contains(offset)
+    }
+
+    /// returns all protected ranges in ascending order. Adjacent or
+    /// overlapping ranges are merged.
+    pub fn protected_ranges(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+        self.protected_ranges.iter()
+    }
+
+    /// returns the parts of `patch` which may be written
+    pub(crate) fn apply_protection(&self, patch: Patch) -> Result<Vec<Patch>, OverlayError> {
+        let range = patch.begin()..patch.end();
+        match self.protected_ranges.overlapping(&range).next() {
+            None => Ok(vec![patch]),
+            Some(protected) => match self.write_policy.protection_mode {
+                ProtectionMode::Reject => Err(OverlayError::ProtectedRegion {
+                    offset: patch.begin(),
+                    len: patch.end() - patch.begin(),
+                    begin: protected.start,
+                    end: protected.end,
+                }),
+                ProtectionMode::Clip => {
+                    let parts: Vec<_> = self
+                        .protected_ranges
+                        .gaps(&range)
+                        .into_iter()
+                        .filter_map(|gap| patch.slice(gap))
+                        .collect();
+
+                    // a write which changes nothing at all must not succeed
+                    if parts.is_empty() {
+                        return Err(OverlayError::ProtectedRegion {
+                            offset: patch.begin(),
+                            len: patch.end() - patch.begin(),
+                            begin: protected.start,
+                            end: protected.end,
+                        });
+                    }
+                    Ok(parts)
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    cmp::{max, min},
+    io::{Read, Result, Seek},
+    ops::Range,
+};
+
+use crate::{ChangeKind, MemOverlay, OverlayError, Patch, PatchLayer, ProtectionMode, RangeSet, SolidPatch};
+
+/// the maximum size of a solid patch which is created while compacting
+const COMPACT_CHUNK_SIZE: u64 = 1024 * 1024;
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// removes all patches in `range`, so that the bytes of the base become
+    /// visible again. Patches which lie only partially inside of `range` are
+    /// shortened. Patches inside of protected regions are handled according
+    /// to [`crate::WritePolicy::protection_mode`], like writes.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::{Cursor, Read};
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    /// overlay.add_bytes_at(0, "HELLO").unwrap();
+    /// overlay.add_bytes_at(7, "PETER").unwrap();
+    /// overlay.revert(3..9).unwrap();
+    ///
+    /// let mut message = String::new();
+    /// overlay.read_to_string(&mut message).unwrap();
+    /// assert_eq!(message, "HELlo, woTER!");
+    /// ```
+    pub fn revert(&mut self, range: Range<u64>) -> Result<()> {
+        // reverting patched bytes of a protected region would change them
+        let protected = self.protected_ranges.overlapping(&range).find(|protected| {
+            self.is_modified(max(protected.start, range.start)..min(protected.end, range.end))
+        });
+
+        match (protected, self.write_policy.protection_mode) {
+            (None, _) => self.revert_unprotected(range),
+            (Some(protected), ProtectionMode::Reject) => Err(OverlayError::ProtectedRegion {
+                offset: range.start,
+                len: range.end - range.start,
+                begin: protected.start,
+                end: protected.end,
+            }
+            .into()),
+            (Some(_), ProtectionMode::Clip) => {
+                for gap in self.protected_ranges.gaps(&range) {
+                    self.revert_unprotected(gap)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn revert_unprotected(&mut self, range: Range<u64>) -> Result<()> {
+        let pending = self.begin_change(range.clone())?;
+
+        let mut changed = false;
+        for layer in self.patch_layers.iter_mut() {
+            changed |= layer.remove_range(&range);
+        }
+        self.patch_layers.retain(|layer| !layer.is_empty());
+
+        if changed {
+            let metadata = self.current_metadata();
+            self.finish_change(pending, ChangeKind::Revert, metadata.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// merges all patch layers into a single layer, which contains only the
+    /// visible parts of all patches. This does not change any visible byte,
+    /// but reduces the memory usage and speeds up reading. Therefore, it is
+    /// not restricted by protected regions.
+    pub fn compact(&mut self) -> Result<()> {
+        let mut patched_ranges = RangeSet::default();
+        let mut segments = Vec::new();
+        for (range, _, patch) in self.effective_segments() {
+            patched_ranges.insert(range.clone());
+            segments.extend(patch.slice(range));
+        }
+
+        // patches which depend on the bytes beneath them cannot be separated
+        // from these bytes, so they are replaced by their visible bytes. This
+        // is done in chunks, so that large regions are never read at once.
+        let mut patches = Vec::new();
+        for patch in segments {
+            if patch.reads_below() {
+                let mut offset = patch.begin();
+                while offset < patch.end() {
+                    let end = min(patch.end(), offset.saturating_add(COMPACT_CHUNK_SIZE));
+                    let bytes = self.read_range(&(offset..end))?;
+                    let mut solid = Patch::new(offset, bytes)?;
+                    if let Some(metadata) = patch.metadata() {
+                        solid = solid.with_metadata(metadata.clone());
+                    }
+                    patches.push(solid);
+                    offset = end;
+                }
+            } else {
+                patches.push(patch);
+            }
+        }
+
+        let mut patches = patches.into_iter();
+        self.patch_layers = match patches.next() {
+            None => Vec::new(),
+            Some(first) => {
+                let mut layer = PatchLayer::new_with(first);
+                for patch in patches {
+                    layer.insert(patch);
+                }
+                vec![layer]
+            }
+        };
+
+        let metadata = self.current_metadata();
+        for range in patched_ranges.iter() {
+            let old_bytes = self.observed_bytes(&range)?;
+            self.notify(ChangeKind::Compaction, range, old_bytes, metadata.as_ref())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    cmp::{max, min},
+    io::{Read, Result, Seek},
+    ops::Range,
+};
+
+use crate::{MemOverlay, OverlayError, SearchPattern};
+
+const SEARCH_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// number of bytes before and after a chunk which are visible to the
+/// assertions of a regular expression. This is enough for a whole UTF-8
+/// encoded character.
+const SEARCH_CONTEXT: u64 = 4;
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// finds all non-overlapping matches of `pattern` in the visible data.
+    /// Matches which span the boundary between base data and patches are
+    /// found as well.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::{MemOverlay, SearchPattern};
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    /// overlay.add_bytes_at(3, "p").unwrap();
+    /// let pattern = SearchPattern::bytes("help").unwrap();
+    /// assert_eq!(overlay.find_all(&pattern).unwrap(), vec![0..4]);
+    /// ```
+    pub fn find_all(&mut self, pattern: &SearchPattern) -> Result<Vec<Range<u64>>> {
+        self.find_in(pattern, 0..self.data_len())
+    }
+
+    /// finds all non-overlapping matches of `pattern` which lie completely
+    /// inside of `range`. The data is searched in chunks; a regular
+    /// expression sees a few bytes before and after every chunk, so that
+    /// assertions like `\b`, `^` and `$` behave as if all of `range` was
+    /// searched at once. Matches which are longer than
+    /// [`SearchPattern::max_len`] may be cut short or missed if they cross
+    /// the end of a chunk.
+    pub fn find_in(&mut self, pattern: &SearchPattern, range: Range<u64>) -> Result<Vec<Range<u64>>> {
+        let end = min(range.end, self.data_len());
+        let overlap = pattern.max_len().saturating_sub(1) as u64;
+
+        let mut matches = Vec::new();
+        let mut chunk_start = range.start;
+        let mut next_match = range.start;
+
+        // the data is read in chunks. Every chunk is extended by the maximum
+        // length of a match, so that matches crossing the end of a chunk are
+        // found as well, and by some context on both sides
+        while chunk_start < end {
+            let chunk_end = min(chunk_start + SEARCH_CHUNK_SIZE, end);
+            let window_start = max(range.start, chunk_start.saturating_sub(SEARCH_CONTEXT));
+            let window_end = min(chunk_end + overlap + SEARCH_CONTEXT, end);
+            let window = self.read_range(&(window_start..window_end))?;
+
+            let mut cursor = (next_match - window_start) as usize;
+            while let Some(found) = pattern.find_from(&window, cursor) {
+                if window_start + found.start as u64 >= chunk_end {
+                    break;
+                }
+                if found.is_empty() {
+                    cursor = found.end + 1;
+                    continue;
+                }
+                matches.push(window_start + found.start as u64..window_start + found.end as u64);
+                cursor = found.end;
+            }
+
+            next_match = max(window_start + cursor as u64, chunk_end);
+            chunk_start = chunk_end;
+        }
+        Ok(matches)
+    }
+
+    /// replaces all matches of `pattern` with `replacement`, and returns the
+    /// number of replacements. Every replacement is recorded as a separate
+    /// patch. The replacement must have the same length as every match,
+    /// otherwise nothing is replaced.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::{Cursor, Read};
+    /// use memoverlay::{MemOverlay, SearchPattern};
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"a1b2c3"));
+    /// let pattern = SearchPattern::hex("?? 32").unwrap();
+    /// assert_eq!(overlay.replace_all(&pattern, "XX").unwrap(), 1);
+    ///
+    /// let mut message = String::new();
+    /// overlay.read_to_string(&mut message).unwrap();
+    /// assert_eq!(message, "a1XXc3");
+    /// ```
+    pub fn replace_all(&mut self, pattern: &SearchPattern, replacement: impl AsRef<[u8]>) -> Result<usize> {
+        let replacement = replacement.as_ref();
+        let matches = self.find_all(pattern)?;
+
+        if let Some(found) = matches
+            .iter()
+            .find(|found| found.end - found.start != replacement.len() as u64)
+        {
+            return Err(OverlayError::ReplacementLengthMismatch {
+                offset: found.start,
+                match_len: found.end - found.start,
+                replacement_len: replacement.len() as u64,
+            }
+            .into());
+        }
+
+        self.atomically(|overlay| {
+            for found in matches.iter() {
+                overlay.write_at(found.start, replacement)?;
+            }
+            Ok(matches.len())
+        })
+    }
+}
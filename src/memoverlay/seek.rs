@@ -14,14 +14,9 @@ fn checked_add(a: u64, b: i64) -> std::io::Result<u64> {
             ))
         }
     } else if let Ok(diff) = TryInto::<u64>::try_into(b) {
-        if let Ok(new_pos) = TryInto::<u64>::try_into(a + diff) {
-            Ok(new_pos)
-        } else {
-            Err(Error::new(
-                ErrorKind::UnexpectedEof,
-                "cannot seek beyond of file",
-            ))
-        }
+        a.checked_add(diff).ok_or_else(|| {
+            Error::new(ErrorKind::UnexpectedEof, "cannot seek beyond of file")
+        })
     } else {
         Err(Error::new(
             ErrorKind::UnexpectedEof,
@@ -41,6 +36,9 @@ where
             std::io::SeekFrom::Start(diff) => self.set_new_position(diff),
 
             std::io::SeekFrom::End(diff) => {
+                if self.base_len_is_stale() {
+                    self.refresh_base_len()?;
+                }
                 self.set_new_position(checked_add(self.last_valid_position(), diff)?)
             }
 
@@ -0,0 +1,40 @@
+use std::io::{Read, Result, Seek};
+
+use crate::{MemOverlay, StringFormat};
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// encodes `s` as described by `format` and writes it at `offset`. Only
+    /// those bytes which change are patched. Returns the number of bytes
+    /// which are occupied by the encoded string, including its length prefix
+    /// and padding.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::{MemOverlay, StringFormat, TextEncoding, LengthPrefix};
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new([0u8; 16]));
+    /// let format = StringFormat::length_prefixed(TextEncoding::Utf8, LengthPrefix::U16Le, 8);
+    /// assert_eq!(overlay.write_str_at(4, "größe", &format).unwrap(), 10);
+    /// assert_eq!(overlay.read_u16_le_at(4).unwrap(), 7);
+    /// assert_eq!(overlay.read_str_at(4, &format).unwrap(), "größe");
+    /// ```
+    pub fn write_str_at(&mut self, offset: u64, s: &str, format: &StringFormat) -> Result<usize> {
+        let bytes = format.encode(s)?;
+        self.update_bytes_at(offset, &bytes)?;
+        Ok(bytes.len())
+    }
+
+    /// reads a string at `offset` which is encoded as described by `format`
+    pub fn read_str_at(&mut self, offset: u64, format: &StringFormat) -> Result<String> {
+        let mut prefix = vec![0; format.prefix_size()];
+        self.read_exact_at(offset, &mut prefix)?;
+
+        let mut content = vec![0; format.content_len(&prefix)?];
+        self.read_exact_at(offset + prefix.len() as u64, &mut content)?;
+        Ok(format.decode(&content)?)
+    }
+}
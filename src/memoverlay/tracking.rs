@@ -0,0 +1,100 @@
+use std::{
+    io::{Read, Result, Seek},
+    ops::Range,
+};
+
+use crate::{ChangeTracker, ChangedBlock, DirtyBitmap, MemOverlay, OverlayError};
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// starts tracking which blocks of `block_size` bytes are changed by
+    /// writes, reverts, aborts and changes of the base encoding, and sets
+    /// the first checkpoint. A block may be reported as changed even if its
+    /// bytes are equal to those at the checkpoint, e.g. if the same bytes
+    /// have been written again or a transaction has been aborted.
+    ///
+    /// Changes which happen outside of the overlay are not noticed, except
+    /// for changes of the length, see [`MemOverlay::invalidate`].
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(vec![0; 4096]));
+    /// overlay.enable_change_tracking(1024).unwrap();
+    /// overlay.add_bytes_at(1020, "peter").unwrap();
+    ///
+    /// let bitmap = overlay.dirty_bitmap().unwrap();
+    /// assert_eq!(bitmap.dirty_blocks().collect::<Vec<_>>(), vec![0, 1]);
+    ///
+    /// let blocks = overlay.export_changed_blocks().unwrap();
+    /// assert_eq!(blocks[1].offset, 1024);
+    /// assert_eq!(&blocks[1].bytes[..1], b"r");
+    ///
+    /// overlay.checkpoint();
+    /// assert_eq!(overlay.dirty_bitmap().unwrap().dirty_count(), 0);
+    /// ```
+    pub fn enable_change_tracking(
+        &mut self,
+        block_size: u64,
+    ) -> std::result::Result<(), OverlayError> {
+        if block_size == 0 {
+            return Err(OverlayError::InvalidBlockSize(block_size));
+        }
+        self.change_tracker = Some(ChangeTracker::new(block_size, self.data_len()));
+        Ok(())
+    }
+
+    pub fn disable_change_tracking(&mut self) {
+        self.change_tracker = None;
+    }
+
+    /// declares that the visible bytes in `range` may have changed without
+    /// the overlay noticing, e.g. because the base has been modified, or the
+    /// source of a patch added by [`MemOverlay::add_external_at`] has changed.
+    /// The blocks in `range` are hashed again by the Merkle tree and reported
+    /// by [`MemOverlay::dirty_bitmap`]. Observers are not informed, because the
+    /// previous bytes are unknown.
+    pub fn invalidate(&mut self, range: Range<u64>) {
+        self.mark_changed(range);
+    }
+
+    /// forgets all changes, so that following calls of
+    /// [`MemOverlay::dirty_bitmap`] report only blocks which are changed
+    /// afterwards
+    pub fn checkpoint(&mut self) {
+        let data_len = self.data_len();
+        if let Some(tracker) = &mut self.change_tracker {
+            *tracker = ChangeTracker::new(tracker.block_size, data_len);
+        }
+    }
+
+    /// returns the blocks which have changed since the last checkpoint, or
+    /// `None` if change tracking is not enabled. Blocks which have been added
+    /// to the end of the data count as changed.
+    pub fn dirty_bitmap(&self) -> Option<DirtyBitmap> {
+        self.change_tracker
+            .as_ref()
+            .map(|tracker| tracker.bitmap(self.data_len()))
+    }
+
+    /// returns the current bytes of all blocks which have changed since the
+    /// last checkpoint, in ascending order of their offsets
+    pub fn export_changed_blocks(&mut self) -> Result<Vec<ChangedBlock>> {
+        let Some(bitmap) = self.dirty_bitmap() else {
+            return Ok(Vec::new());
+        };
+        let mut blocks = Vec::new();
+        for index in bitmap.dirty_blocks() {
+            let range = bitmap.block_range(index);
+            blocks.push(ChangedBlock {
+                offset: range.start,
+                bytes: self.read_range(&range)?,
+            });
+        }
+        Ok(blocks)
+    }
+}
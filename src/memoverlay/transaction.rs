@@ -0,0 +1,154 @@
+use std::io::{Read, Seek};
+use std::sync::Arc;
+
+use crate::{BaseEncoding, ChangeEvent, ChangeKind, ChangeTracker, MemOverlay, OverlayError, PatchLayer, RangeSet};
+
+/// the state which is needed to roll back a transaction
+#[derive(Clone)]
+pub(crate) struct TransactionState {
+    patch_layers: Vec<PatchLayer>,
+    base_encoding: Option<Arc<dyn BaseEncoding>>,
+    change_tracker: Option<ChangeTracker>,
+    pub(crate) pending_events: Vec<ChangeEvent>,
+
+    /// all ranges which have been changed inside of the transaction
+    pub(crate) changed: RangeSet,
+}
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// starts a transaction. All following changes can either be kept with
+    /// [`MemOverlay::commit`] or be undone with [`MemOverlay::abort`].
+    /// Observers are not informed about changes before the transaction has
+    /// been committed.
+    pub fn begin(&mut self) -> Result<(), OverlayError> {
+        if self.transaction.is_some() {
+            return Err(OverlayError::TransactionActive);
+        }
+        self.transaction = Some(Box::new(TransactionState {
+            patch_layers: self.patch_layers.clone(),
+            base_encoding: self.base_encoding.clone(),
+            change_tracker: self.change_tracker.clone(),
+            pending_events: Vec::new(),
+            changed: RangeSet::default(),
+        }));
+        Ok(())
+    }
+
+    /// keeps all changes of the current transaction
+    pub fn commit(&mut self) -> Result<(), OverlayError> {
+        match self.transaction.take() {
+            None => Err(OverlayError::NoTransaction),
+            Some(transaction) => {
+                for event in transaction.pending_events.iter() {
+                    self.dispatch(event);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// undoes all changes of the current transaction. The changed ranges are
+    /// reported to the observers with [`ChangeKind::Abort`], and the change
+    /// tracking and the encoding of the base are reset to their state at the
+    /// beginning of the transaction.
+    pub fn abort(&mut self) -> Result<(), OverlayError> {
+        let Some(transaction) = self.transaction.take() else {
+            return Err(OverlayError::NoTransaction);
+        };
+
+        // the bytes of the transaction can only be read before they are gone
+        let pending: Vec<_> = transaction
+            .changed
+            .iter()
+            .map(|range| self.begin_change(range))
+            .collect();
+
+        self.patch_layers = transaction.patch_layers;
+        self.base_encoding = transaction.base_encoding;
+        self.change_tracker = transaction.change_tracker;
+        for range in transaction.changed.iter() {
+            self.mark_changed(range);
+        }
+        let metadata = self.current_metadata();
+        for pending in pending {
+            self.finish_change(pending?, ChangeKind::Abort, metadata.as_ref())?;
+        }
+        Ok(())
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// runs `f` inside of a new transaction, or as part of the current
+    /// transaction if there is already one. In both cases, all changes done
+    /// by `f` are undone if it fails.
+    pub(crate) fn atomically<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+        E: From<OverlayError>,
+    {
+        let Some(transaction) = &self.transaction else {
+            return self.transaction(f);
+        };
+
+        // a savepoint inside of the current transaction. The ranges which
+        // have been changed by `f` stay marked for the change tracking.
+        let patch_layers = self.patch_layers.clone();
+        let base_encoding = self.base_encoding.clone();
+        let changed = transaction.changed.clone();
+        let event_count = transaction.pending_events.len();
+
+        let result = f(self);
+        if result.is_err() {
+            self.patch_layers = patch_layers;
+            self.base_encoding = base_encoding;
+            if let Some(transaction) = &mut self.transaction {
+                transaction.changed = changed;
+                transaction.pending_events.truncate(event_count);
+            }
+        }
+        result
+    }
+
+    /// runs `f` inside of a transaction. If `f` returns an error, all changes
+    /// done by `f` are undone.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::{self, Cursor, Read};
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    /// let result = overlay.transaction(|tx| {
+    ///     tx.add_bytes_at(0, "HELLO")?;
+    ///     tx.add_bytes_at(20, "!")?;
+    ///     Ok::<_, io::Error>(())
+    /// });
+    /// assert!(result.is_err());
+    ///
+    /// let mut message = String::new();
+    /// overlay.read_to_string(&mut message).unwrap();
+    /// assert_eq!(message, "hello, world!");
+    /// ```
+    pub fn transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+        E: From<OverlayError>,
+    {
+        self.begin()?;
+        match f(self) {
+            Ok(result) => {
+                self.commit()?;
+                Ok(result)
+            }
+            Err(why) => {
+                self.abort()?;
+                Err(why)
+            }
+        }
+    }
+}
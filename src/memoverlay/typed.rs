@@ -0,0 +1,277 @@
+use std::io::{Error, ErrorKind, Read, Result, Seek};
+
+use crate::{MemOverlay, OverlayError};
+
+macro_rules! typed_accessors {
+    ($($ty:ty => $read_le:ident, $read_be:ident, $write_le:ident, $write_be:ident;)*) => {
+        $(
+            #[doc = concat!("reads a little endian `", stringify!($ty), "` at `offset`")]
+            pub fn $read_le(&mut self, offset: u64) -> Result<$ty> {
+                let mut buf = [0; std::mem::size_of::<$ty>()];
+                self.read_exact_at(offset, &mut buf)?;
+                Ok(<$ty>::from_le_bytes(buf))
+            }
+
+            #[doc = concat!("reads a big endian `", stringify!($ty), "` at `offset`")]
+            pub fn $read_be(&mut self, offset: u64) -> Result<$ty> {
+                let mut buf = [0; std::mem::size_of::<$ty>()];
+                self.read_exact_at(offset, &mut buf)?;
+                Ok(<$ty>::from_be_bytes(buf))
+            }
+
+            #[doc = concat!("writes `value` as little endian `", stringify!($ty), "` at `offset`, see [`MemOverlay::update_bytes_at`]")]
+            pub fn $write_le(&mut self, offset: u64, value: $ty) -> Result<()> {
+                self.update_bytes_at(offset, value.to_le_bytes()).map(|_| ())
+            }
+
+            #[doc = concat!("writes `value` as big endian `", stringify!($ty), "` at `offset`, see [`MemOverlay::update_bytes_at`]")]
+            pub fn $write_be(&mut self, offset: u64, value: $ty) -> Result<()> {
+                self.update_bytes_at(offset, value.to_be_bytes()).map(|_| ())
+            }
+        )*
+    };
+}
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// fills `buf` with the visible bytes at `offset`, without changing the
+    /// current position. Fails if there are not enough bytes.
+    pub fn read_exact_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        if self.read_at(offset, buf)? < buf.len() {
+            Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "cannot read beyond end of file",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// writes `bytes` at `offset`, but creates patches only for those bytes
+    /// which differ from the currently visible bytes. Either all of these
+    /// patches are created, or none of them. Returns the number of bytes
+    /// which have been changed.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    /// assert_eq!(overlay.update_bytes_at(7, "wirld").unwrap(), 1);
+    /// assert_eq!(overlay.patch_bytes(), 1);
+    /// ```
+    pub fn update_bytes_at(&mut self, offset: u64, bytes: impl AsRef<[u8]>) -> Result<usize> {
+        let bytes = bytes.as_ref();
+        let mut current = vec![0; bytes.len()];
+        let available = self.read_at(offset, &mut current)?;
+        current.truncate(available);
+
+        // find all runs of bytes which differ from the current bytes
+        let mut runs = Vec::new();
+        let mut run_start = None;
+        for (index, byte) in bytes.iter().enumerate() {
+            let changed = current.get(index) != Some(byte);
+            match (changed, run_start) {
+                (true, None) => run_start = Some(index),
+                (false, Some(start)) => {
+                    runs.push(start..index);
+                    run_start = None;
+                }
+                _ => (),
+            }
+        }
+        if let Some(start) = run_start {
+            runs.push(start..bytes.len());
+        }
+
+        let changed_bytes = runs.iter().map(|run| run.len()).sum();
+        match runs.as_slice() {
+            [] => (),
+            [run] => {
+                self.write_at(offset + run.start as u64, &bytes[run.clone()])?;
+            }
+            _ => self.atomically(|overlay| -> Result<()> {
+                for run in runs.iter() {
+                    overlay.write_at(offset + run.start as u64, &bytes[run.clone()])?;
+                }
+                Ok(())
+            })?,
+        }
+        Ok(changed_bytes)
+    }
+
+    /// writes `new` at `offset`, but only if the currently visible bytes at
+    /// `offset` are `expected`. Otherwise, nothing is changed and an
+    /// [`OverlayError::UnexpectedBytes`] error is returned, which contains
+    /// the actual bytes.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::{MemOverlay, OverlayError};
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"version 1.2"));
+    /// overlay.write_if_matches(8, "1.2", "1.3").unwrap();
+    ///
+    /// let err = overlay.write_if_matches(8, "1.2", "1.4").unwrap_err();
+    /// let err = err.into_inner().unwrap().downcast::<OverlayError>().unwrap();
+    /// assert!(matches!(*err, OverlayError::UnexpectedBytes { ref actual, .. } if actual == b"1.3"));
+    /// ```
+    pub fn write_if_matches(
+        &mut self,
+        offset: u64,
+        expected: impl AsRef<[u8]>,
+        new: impl AsRef<[u8]>,
+    ) -> Result<()> {
+        let expected = expected.as_ref();
+        let actual = self.read_range(&(offset..offset + expected.len() as u64))?;
+        if actual != expected {
+            return Err(OverlayError::UnexpectedBytes {
+                offset,
+                expected: expected.to_vec(),
+                actual,
+            }
+            .into());
+        }
+        self.write_at(offset, new.as_ref()).map(|_| ())
+    }
+
+    /// reads a single byte at `offset`
+    pub fn read_u8_at(&mut self, offset: u64) -> Result<u8> {
+        let mut buf = [0; 1];
+        self.read_exact_at(offset, &mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// writes a single byte at `offset`, see [`MemOverlay::update_bytes_at`]
+    pub fn write_u8_at(&mut self, offset: u64, value: u8) -> Result<()> {
+        self.update_bytes_at(offset, [value]).map(|_| ())
+    }
+
+    /// reads a single signed byte at `offset`
+    pub fn read_i8_at(&mut self, offset: u64) -> Result<i8> {
+        self.read_u8_at(offset).map(|value| value as i8)
+    }
+
+    /// writes a single signed byte at `offset`, see
+    /// [`MemOverlay::update_bytes_at`]
+    pub fn write_i8_at(&mut self, offset: u64, value: i8) -> Result<()> {
+        self.write_u8_at(offset, value as u8)
+    }
+
+    typed_accessors! {
+        u16 => read_u16_le_at, read_u16_be_at, write_u16_le_at, write_u16_be_at;
+        u32 => read_u32_le_at, read_u32_be_at, write_u32_le_at, write_u32_be_at;
+        u64 => read_u64_le_at, read_u64_be_at, write_u64_le_at, write_u64_be_at;
+        u128 => read_u128_le_at, read_u128_be_at, write_u128_le_at, write_u128_be_at;
+        i16 => read_i16_le_at, read_i16_be_at, write_i16_le_at, write_i16_be_at;
+        i32 => read_i32_le_at, read_i32_be_at, write_i32_le_at, write_i32_be_at;
+        i64 => read_i64_le_at, read_i64_be_at, write_i64_le_at, write_i64_be_at;
+        i128 => read_i128_le_at, read_i128_be_at, write_i128_le_at, write_i128_be_at;
+        f32 => read_f32_le_at, read_f32_be_at, write_f32_le_at, write_f32_be_at;
+        f64 => read_f64_le_at, read_f64_be_at, write_f64_le_at, write_f64_be_at;
+    }
+
+    /// reads an unsigned LEB128 value at `offset`. Returns the value and the
+    /// number of bytes it occupies.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::MemOverlay;
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new([0u8; 8]));
+    /// assert_eq!(overlay.write_uleb128_at(2, 624485).unwrap(), 3);
+    /// assert_eq!(overlay.read_uleb128_at(2).unwrap(), (624485, 3));
+    /// ```
+    pub fn read_uleb128_at(&mut self, offset: u64) -> Result<(u64, usize)> {
+        let mut value = 0u64;
+        let mut length = 0;
+        loop {
+            let byte = self.read_u8_at(offset + length as u64)?;
+            let shift = 7 * length;
+            if shift >= 64 || (shift == 63 && byte & 0x7e != 0) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "LEB128 value does not fit into 64 bits",
+                ));
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            length += 1;
+            if byte & 0x80 == 0 {
+                return Ok((value, length));
+            }
+        }
+    }
+
+    /// reads a signed LEB128 value at `offset`. Returns the value and the
+    /// number of bytes it occupies.
+    pub fn read_sleb128_at(&mut self, offset: u64) -> Result<(i64, usize)> {
+        let mut value = 0i64;
+        let mut length = 0;
+        loop {
+            let byte = self.read_u8_at(offset + length as u64)?;
+            let shift = 7 * length;
+            if shift >= 64 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "LEB128 value does not fit into 64 bits",
+                ));
+            }
+            value |= i64::from(byte & 0x7f) << shift;
+            length += 1;
+            if byte & 0x80 == 0 {
+                if shift + 7 < 64 && byte & 0x40 != 0 {
+                    value |= -1 << (shift + 7);
+                }
+                return Ok((value, length));
+            }
+        }
+    }
+
+    /// writes `value` as unsigned LEB128 at `offset`. Returns the number of
+    /// bytes which have been written.
+    pub fn write_uleb128_at(&mut self, offset: u64, value: u64) -> Result<usize> {
+        let bytes = encode_uleb128(value);
+        self.update_bytes_at(offset, &bytes)?;
+        Ok(bytes.len())
+    }
+
+    /// writes `value` as signed LEB128 at `offset`. Returns the number of
+    /// bytes which have been written.
+    pub fn write_sleb128_at(&mut self, offset: u64, value: i64) -> Result<usize> {
+        let bytes = encode_sleb128(value);
+        self.update_bytes_at(offset, &bytes)?;
+        Ok(bytes.len())
+    }
+}
+
+fn encode_uleb128(mut value: u64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn encode_sleb128(mut value: i64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        if done {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
@@ -1,37 +1,127 @@
-use std::io::{Write, Read, Seek, ErrorKind, self};
+use std::io::{Write, Read, Seek, self};
 
-use crate::{MemOverlay, Patch, SolidPatch, PatchLayer};
+use crate::{ChangeKind, MemOverlay, OverlayError, Patch, SolidPatch, PatchLayer};
 
-impl<R> Write for MemOverlay<R>
+impl<R> MemOverlay<R>
 where
     R: Read + Seek,
 {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let patch = match Patch::new(self.pos, buf) {
-            Ok(patch) => patch,
-            Err(err) => return Err(io::Error::new(ErrorKind::InvalidData, err)),
-        };
+    /// creates a patch at `offset`, without changing the current position
+    pub(crate) fn write_at(&mut self, offset: u64, buf: &[u8]) -> io::Result<usize> {
+        let patch = Patch::new(offset, buf)?;
+        self.add_patch(patch)?;
+        Ok(buf.len())
+    }
 
-        // find the first layer where this patch does not overlap with any other patch
-        match self
-            .patch_layers
-            .iter_mut()
-            .rev()
-            .find(|layer| layer.may_contain(&patch))
-        {
-            Some(layer) => {
-                layer.insert(patch);
+    /// adds a patch which has been created by one of the constructors of
+    /// [`Patch`], e.g. to attach metadata to a single patch with
+    /// [`Patch::with_metadata`]. The patch is checked against the protected
+    /// regions and the write policy, and inserted into the patch layers.
+    /// Either the whole patch is inserted, or nothing at all.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::{MemOverlay, Patch, PatchMetadata};
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    /// let patch = Patch::fill(7, "-", 5).unwrap().with_metadata(PatchMetadata::new("alice"));
+    /// overlay.add_patch(patch).unwrap();
+    ///
+    /// assert_eq!(overlay.read_range(&(0..13)).unwrap(), b"hello, -----!");
+    /// assert_eq!(overlay.blame(7).unwrap().metadata().unwrap().author, "alice");
+    /// ```
+    pub fn add_patch(&mut self, patch: Patch) -> io::Result<()> {
+        let patch = self.attach_metadata(patch);
+        let patches = self.apply_protection(patch)?;
+
+        let mut pending_bytes = 0;
+        for patch in patches.iter() {
+            self.check_write_policy(patch, pending_bytes)?;
+            pending_bytes += patch.end() - patch.begin();
+        }
+
+        for patch in patches {
+            let pending = self.begin_change(patch.begin()..patch.end())?;
+            let metadata = patch.metadata().cloned();
+            self.insert_patch(patch);
+            self.finish_change(pending, ChangeKind::Write, metadata.as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn check_write_policy(&mut self, patch: &Patch, pending_bytes: u64) -> io::Result<()> {
+        let offset = patch.begin();
+        let len = patch.end() - patch.begin();
+
+        if (offset > self.data_len() || patch.end() > self.base_len) && self.base_len_is_stale() {
+            self.refresh_base_len()?;
+        }
+
+        if !self.write_policy.allow_gap && offset > self.data_len() {
+            return Err(OverlayError::WriteBeyondEnd {
+                offset,
+                end: self.data_len(),
             }
-            None => {
-                let layer = PatchLayer::new_with(patch);
+            .into());
+        }
 
-                // insert at position 0 to make sure that a call to read()
-                // always accesses the most recent patches first
-                self.patch_layers.insert(0, layer);
+        if !self.write_policy.allow_growth && patch.end() > self.base_len {
+            return Err(OverlayError::GrowthForbidden {
+                offset,
+                len,
+                base_len: self.base_len,
             }
+            .into());
         }
 
-        Ok(buf.len())
+        if !self.write_policy.allow_overlap
+            && self
+                .patch_layers
+                .iter()
+                .any(|layer| !layer.may_contain(patch))
+        {
+            return Err(OverlayError::OverlappingWrite { offset, len }.into());
+        }
+
+        if let Some(limit) = self.write_policy.max_patch_bytes {
+            let required = self.patch_bytes() + pending_bytes + len;
+            if required > limit {
+                return Err(OverlayError::PatchLimitExceeded { required, limit }.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn insert_patch(&mut self, patch: Patch) {
+        // find the lowest layer where this patch does not overlap with any
+        // other patch in this layer or in any layer above it. Otherwise, an
+        // older patch could hide the new one
+        let depth = self
+            .patch_layers
+            .iter()
+            .take_while(|layer| layer.may_contain(&patch))
+            .count();
+
+        if depth == 0 {
+            let layer = PatchLayer::new_with(patch);
+
+            // insert at position 0 to make sure that a call to read()
+            // always accesses the most recent patches first
+            self.patch_layers.insert(0, layer);
+        } else {
+            self.patch_layers[depth - 1].insert(patch);
+        }
+    }
+}
+
+impl<R> Write for MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_at(self.pos, buf)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
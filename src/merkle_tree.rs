@@ -0,0 +1,152 @@
+use std::{collections::BTreeSet, ops::Range};
+
+use sha2::{Digest, Sha256};
+
+use crate::RangeSet;
+
+/// a hash tree over fixed-size blocks of the visible data of a
+/// [`crate::MemOverlay`], see [`crate::MemOverlay::enable_merkle_tree`].
+///
+/// Every leaf is the SHA-256 hash of a `0x00` byte followed by the bytes of
+/// a block; the last block may be shorter than the others. Every inner node
+/// is the SHA-256 hash of a `0x01` byte followed by the hashes of its two
+/// children. A node without a sibling is moved up unchanged.
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    block_size: u64,
+    data_len: u64,
+
+    /// all levels of the tree, starting with the leaves
+    levels: Vec<Vec<[u8; 32]>>,
+
+    /// the ranges of bytes which have changed since the last update
+    dirty: RangeSet,
+}
+
+impl MerkleTree {
+    pub(crate) fn new(block_size: u64) -> Self {
+        Self {
+            block_size,
+            data_len: 0,
+            levels: vec![Vec::new()],
+            dirty: RangeSet::default(),
+        }
+    }
+
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// returns the number of bytes which are covered by the tree
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// returns the hash of the block with the given index
+    pub fn block_hash(&self, index: usize) -> Option<[u8; 32]> {
+        self.levels[0].get(index).copied()
+    }
+
+    /// returns the hash which covers all blocks. If there is no data at all,
+    /// this is the hash of an empty block.
+    pub fn root(&self) -> [u8; 32] {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(root) => *root,
+            None => leaf_hash(&[]),
+        }
+    }
+
+    /// returns the range of the bytes in the block with the given index
+    pub fn block_range(&self, index: usize) -> Range<u64> {
+        let begin = index as u64 * self.block_size;
+        begin..(begin + self.block_size).min(self.data_len)
+    }
+
+    pub(crate) fn mark_dirty(&mut self, range: Range<u64>) {
+        self.dirty.insert(range);
+    }
+
+    /// returns the indices of all blocks which need to be hashed again if the
+    /// data has `data_len` bytes now
+    pub(crate) fn dirty_blocks(&self, data_len: u64) -> BTreeSet<usize> {
+        let block_count = data_len.div_ceil(self.block_size);
+        let mut blocks = BTreeSet::new();
+        for range in self.dirty.iter() {
+            let first = range.start / self.block_size;
+            let last = range.end.div_ceil(self.block_size).min(block_count);
+            blocks.extend((first..last).map(|index| index as usize));
+        }
+
+        // the previous last block may have grown or shrunk
+        if data_len != self.data_len {
+            let first = self.data_len.min(data_len) / self.block_size;
+            blocks.extend((first..block_count).map(|index| index as usize));
+        }
+        blocks
+    }
+
+    /// stores the new hashes of the blocks which have changed, and updates
+    /// all nodes above them
+    pub(crate) fn update(&mut self, data_len: u64, blocks: Vec<(usize, [u8; 32])>) {
+        let resized = data_len != self.data_len;
+        self.data_len = data_len;
+        self.dirty = RangeSet::default();
+
+        let block_count = data_len.div_ceil(self.block_size) as usize;
+        self.levels[0].resize(block_count, [0; 32]);
+        let mut changed = BTreeSet::new();
+        for (index, hash) in blocks {
+            self.levels[0][index] = hash;
+            changed.insert(index);
+        }
+
+        if resized {
+            self.rebuild();
+            return;
+        }
+        for level in 1..self.levels.len() {
+            changed = changed.into_iter().map(|index| index / 2).collect();
+            for index in changed.iter() {
+                self.levels[level][*index] = self.parent_hash(level - 1, *index);
+            }
+        }
+    }
+
+    /// computes all nodes above the leaves
+    fn rebuild(&mut self) {
+        self.levels.truncate(1);
+        while self.levels.last().unwrap().len() > 1 {
+            let below = self.levels.len() - 1;
+            let len = self.levels[below].len().div_ceil(2);
+            let level = (0..len)
+                .map(|index| self.parent_hash(below, index))
+                .collect();
+            self.levels.push(level);
+        }
+    }
+
+    fn parent_hash(&self, below: usize, index: usize) -> [u8; 32] {
+        let children = &self.levels[below];
+        match children.get(2 * index + 1) {
+            None => children[2 * index],
+            Some(right) => {
+                let mut hasher = Sha256::new();
+                hasher.update([1]);
+                hasher.update(children[2 * index]);
+                hasher.update(right);
+                hasher.finalize().into()
+            }
+        }
+    }
+}
+
+pub(crate) fn leaf_hash(block: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0]);
+    hasher.update(block);
+    hasher.finalize().into()
+}
@@ -1,6 +1,20 @@
-use std::{hash::Hash, io::{Cursor, Seek, SeekFrom, Read}};
+use std::{
+    cmp::min,
+    hash::Hash,
+    io::{Error, ErrorKind, Read, Seek, SeekFrom},
+    ops::Range,
+    sync::{Arc, Mutex},
+};
 
-use crate::{Contains, OverlayError, SolidPatch};
+use crate::{BaseEncoding, ByteTransform, Contains, OverlayError, PatchLayer, PatchMetadata, SolidPatch};
+
+/// something which can supply the content of a patch, see [`Patch::external`]
+pub trait PatchSource: Read + Seek + Send {}
+
+impl<T> PatchSource for T where T: Read + Seek + Send {}
+
+/// a [`PatchSource`] which can be shared between multiple patches
+pub type SharedSource = Arc<Mutex<dyn PatchSource>>;
 
 /// represents a memory patch. It is not allowed to create an empty patch
 /// 
@@ -14,19 +28,275 @@ use crate::{Contains, OverlayError, SolidPatch};
 #[derive(Clone)]
 pub struct Patch {
     offset: u64,
-    content: Vec<u8>,
+    content: PatchContent,
+    metadata: Option<Arc<PatchMetadata>>,
+}
+
+/// decides what a copy reads when its source is changed later, see
+/// [`crate::MemOverlay::copy_within`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyMode {
+    /// the copy always shows the source as it was when the copy was created,
+    /// even if the encoding or the length of the base change later. The
+    /// base itself is not copied, so changes of the stored base are visible,
+    /// see [`crate::MemOverlay::invalidate`].
+    Snapshot,
+
+    /// the copy always shows the current data of its source. A live copy
+    /// must not show itself, neither directly nor through other live copies.
+    Live,
+}
+
+/// the state of the data when a snapshot copy has been created, see
+/// [`CopyMode::Snapshot`]
+pub(crate) struct Snapshot {
+    pub(crate) layers: Vec<PatchLayer>,
+    pub(crate) base_len: u64,
+    pub(crate) base_encoding: Option<Arc<dyn BaseEncoding>>,
+}
+
+/// the data of a [`Patch`]
+#[derive(Clone)]
+pub(crate) enum PatchContent {
+    /// the patch owns all of its bytes. They are shared between clones of
+    /// the patch
+    Bytes(Arc<[u8]>),
+
+    /// the patch repeats `pattern` until it reaches a length of `len` bytes
+    Fill { pattern: Vec<u8>, len: u64 },
+
+    /// the patch contains the bytes in `range` of `source`, which are read
+    /// only when they are needed
+    External { source: SharedSource, range: Range<u64> },
+
+    /// the patch shows `len` bytes of the data starting at `source`. If there
+    /// is a snapshot, this data is read from the snapshot and the base,
+    /// otherwise it is read from the current data of the overlay
+    Reference {
+        source: u64,
+        len: u64,
+        snapshot: Option<Arc<Snapshot>>,
+    },
+
+    /// the patch replaces only the bits which are set in `mask`, all other
+    /// bits are taken from the bytes beneath the patch
+    Masked { value: Arc<[u8]>, mask: Arc<[u8]> },
+
+    /// the patch shows the bytes in `input` beneath it, after `transform`
+    /// has been applied to them. The patch itself may cover only a part of
+    /// `input`, if it has been sliced.
+    Computed {
+        transform: Arc<dyn ByteTransform>,
+        input: Range<u64>,
+        len: u64,
+    },
 }
 
 impl Patch {
+    /// creates a patch which repeats `pattern` until it reaches a length of
+    /// `len` bytes. This needs only the memory for `pattern`, regardless of
+    /// `len`.
+    ///
+    /// # Example
+    /// ```
+    /// use memoverlay::Patch;
+    ///
+    /// let patch = Patch::fill(10, &[0xde, 0xad], 1 << 40).unwrap();
+    /// assert_eq!(patch.len(), 1 << 40);
+    ///
+    /// let mut buf = [0; 3];
+    /// patch.read(1, &mut buf).unwrap();
+    /// assert_eq!(buf, [0xad, 0xde, 0xad]);
+    /// assert!(Patch::fill(10, &[], 100).is_err());
+    /// ```
+    pub fn fill(offset: u64, pattern: impl AsRef<[u8]>, len: u64) -> Result<Self, OverlayError> {
+        let pattern = pattern.as_ref();
+        if pattern.is_empty() || len == 0 {
+            Err(OverlayError::EmptyPatch)
+        } else {
+            check_end(offset, len)?;
+            Ok(Self {
+                offset,
+                content: PatchContent::Fill {
+                    pattern: pattern.to_vec(),
+                    len,
+                },
+                metadata: None,
+            })
+        }
+    }
+
+    /// creates a patch which contains the bytes in `range` of `source`. The
+    /// bytes are not copied, but read from `source` whenever they are needed.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use std::sync::{Arc, Mutex};
+    /// use memoverlay::{Patch, SharedSource};
+    ///
+    /// let source: SharedSource = Arc::new(Mutex::new(Cursor::new(b"hello, world!")));
+    /// let patch = Patch::external(100, source, 7..12).unwrap();
+    /// assert_eq!(patch.end(), 105);
+    ///
+    /// let mut buf = [0; 4];
+    /// patch.read(1, &mut buf).unwrap();
+    /// assert_eq!(&buf, b"orld");
+    /// ```
+    pub fn external(offset: u64, source: SharedSource, range: Range<u64>) -> Result<Self, OverlayError> {
+        if range.is_empty() {
+            Err(OverlayError::EmptyPatch)
+        } else {
+            check_end(offset, range.end - range.start)?;
+            Ok(Self {
+                offset,
+                content: PatchContent::External { source, range },
+                metadata: None,
+            })
+        }
+    }
+
+    /// creates a patch which changes only some bits. Wherever a bit of `mask`
+    /// is set, the corresponding bit of `value` is visible, otherwise the bit
+    /// of whatever lies beneath the patch: `(below & !mask) | (value & mask)`
+    ///
+    /// # Example
+    /// ```
+    /// use memoverlay::Patch;
+    ///
+    /// let patch = Patch::masked(4, [0x80, 0x01], [0xc0, 0x0f]).unwrap();
+    /// assert_eq!(patch.end(), 6);
+    /// assert!(Patch::masked(4, [0x80, 0x01], [0xc0]).is_err());
+    /// ```
+    pub fn masked(offset: u64, value: impl AsRef<[u8]>, mask: impl AsRef<[u8]>) -> Result<Self, OverlayError> {
+        let value = value.as_ref();
+        let mask = mask.as_ref();
+        if value.len() != mask.len() {
+            Err(OverlayError::MaskLengthMismatch {
+                value_len: value.len(),
+                mask_len: mask.len(),
+            })
+        } else if value.is_empty() {
+            Err(OverlayError::EmptyPatch)
+        } else {
+            check_end(offset, value.len() as u64)?;
+            Ok(Self {
+                offset,
+                content: PatchContent::Masked {
+                    value: Arc::from(value),
+                    mask: Arc::from(mask),
+                },
+                metadata: None,
+            })
+        }
+    }
+
+    /// creates a patch of `len` bytes, whose content is computed from the
+    /// bytes beneath it, whenever it is read. The transform always sees all
+    /// of these bytes, so it should be used only for small patches.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Cursor;
+    /// use memoverlay::{AddLe, MemOverlay};
+    ///
+    /// let mut overlay = MemOverlay::from(Cursor::new([0xffu8, 0x00, 0x00, 0x00]));
+    /// overlay.transform_at(0, 4, AddLe(1)).unwrap();
+    /// assert_eq!(overlay.read_u32_le_at(0).unwrap(), 0x100);
+    /// ```
+    pub fn computed(offset: u64, len: u64, transform: impl ByteTransform + 'static) -> Result<Self, OverlayError> {
+        if len == 0 {
+            Err(OverlayError::EmptyPatch)
+        } else {
+            check_end(offset, len)?;
+            Ok(Self {
+                offset,
+                content: PatchContent::Computed {
+                    transform: Arc::new(transform),
+                    input: offset..offset + len,
+                    len,
+                },
+                metadata: None,
+            })
+        }
+    }
+
+    /// creates a patch which shows the `len` bytes starting at `source`
+    pub(crate) fn reference(
+        offset: u64,
+        source: u64,
+        len: u64,
+        snapshot: Option<Arc<Snapshot>>,
+    ) -> Result<Self, OverlayError> {
+        if len == 0 {
+            Err(OverlayError::EmptyPatch)
+        } else {
+            check_end(offset, len)?;
+            check_end(source, len)?;
+            Ok(Self {
+                offset,
+                content: PatchContent::Reference {
+                    source,
+                    len,
+                    snapshot,
+                },
+                metadata: None,
+            })
+        }
+    }
+
+    /// attaches `metadata` to this patch
+    pub fn with_metadata(self, metadata: PatchMetadata) -> Self {
+        Self {
+            metadata: Some(Arc::new(metadata)),
+            ..self
+        }
+    }
+
+    pub fn metadata(&self) -> Option<&PatchMetadata> {
+        self.metadata.as_deref()
+    }
+
+    pub(crate) fn content(&self) -> &PatchContent {
+        &self.content
+    }
+
+    /// returns `true` if the bytes of this patch depend on the bytes beneath
+    /// it, so that it must stay above them
+    pub(crate) fn reads_below(&self) -> bool {
+        matches!(
+            self.content,
+            PatchContent::Masked { .. } | PatchContent::Computed { .. }
+        )
+    }
+
     /// returns a unique id of this patch. Two patches have the same id if they
     /// have the same position and the same length. If two patches have the same
     /// offset, than the patch with the greater length has te greater id
     pub fn id(&self) -> u128 {
         let offset = (self.offset as u128) << 64;
-        let len = TryInto::<u128>::try_into(self.content.len()).unwrap();
+        let len = self.len() as u128;
         offset | len
     }
 
+    /// returns the number of bytes of this patch
+    pub fn len(&self) -> u64 {
+        match &self.content {
+            PatchContent::Bytes(content) => TryInto::<u64>::try_into(content.len()).unwrap(),
+            PatchContent::Fill { len, .. } => *len,
+            PatchContent::External { range, .. } => range.end - range.start,
+            PatchContent::Reference { len, .. } => *len,
+            PatchContent::Masked { value, .. } => TryInto::<u64>::try_into(value.len()).unwrap(),
+            PatchContent::Computed { len, .. } => *len,
+        }
+    }
+
+    /// returns `true` if the patch has no bytes, which is never the case for
+    /// a patch which has been created successfully
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// returns the offset of the first byte of this patch
     pub fn begin(&self) -> u64 {
         self.offset
@@ -34,7 +304,7 @@ impl Patch {
 
     /// returns the offset of the first byte after this patch
     pub fn end(&self) -> u64 {
-        self.offset + TryInto::<u64>::try_into(self.content.len()).unwrap()
+        self.offset + self.len()
     }
 
     /// returns the offset of the first byte of this patch
@@ -45,8 +315,8 @@ impl Patch {
     /// returns the offset of the last byte of this patch, or `None` if the patch
     /// has a length of *zero*
     pub fn last_byte_offset(&self) -> u64 {
-        assert!(! self.content.is_empty());
-        self.offset + TryInto::<u64>::try_into(self.content.len() - 1).unwrap()
+        assert!(! self.is_empty());
+        self.offset + self.len() - 1
     }
 
     /// checks if two patches overlap each other
@@ -73,10 +343,128 @@ impl Patch {
         other.contains(self.first_byte_offset()) || self.contains(other.first_byte_offset())
     }
 
+    /// returns the part of this patch which lies within `range`, or `None` if
+    /// the patch has no bytes in this range
+    ///
+    /// # Example
+    /// ```
+    /// use memoverlay::{SolidPatch, Patch};
+    ///
+    /// let patch = Patch::new(10, &[0,1,2,3,4,5,6,7,8,9][..]).unwrap();
+    /// let slice = patch.slice(15..30).unwrap();
+    /// assert_eq!(slice.begin(), 15);
+    /// assert_eq!(slice.end(), 20);
+    /// assert!(patch.slice(0..10).is_none());
+    /// ```
+    pub fn slice(&self, range: Range<u64>) -> Option<Self> {
+        let begin = std::cmp::max(range.start, self.begin());
+        let end = std::cmp::min(range.end, self.end());
+        if begin >= end {
+            return None;
+        }
+        let content = match &self.content {
+            PatchContent::Bytes(content) => {
+                let from: usize = (begin - self.offset).try_into().unwrap();
+                let to: usize = (end - self.offset).try_into().unwrap();
+                PatchContent::Bytes(Arc::from(&content[from..to]))
+            }
+            PatchContent::Fill { pattern, .. } => {
+                // keep the phase of the pattern
+                let shift = ((begin - self.offset) % pattern.len() as u64) as usize;
+                let mut pattern = pattern.clone();
+                pattern.rotate_left(shift);
+                PatchContent::Fill {
+                    pattern,
+                    len: end - begin,
+                }
+            }
+            PatchContent::External { source, range } => PatchContent::External {
+                source: Arc::clone(source),
+                range: range.start + (begin - self.offset)..range.start + (end - self.offset),
+            },
+            PatchContent::Reference {
+                source, snapshot, ..
+            } => PatchContent::Reference {
+                source: source + (begin - self.offset),
+                len: end - begin,
+                snapshot: snapshot.clone(),
+            },
+            PatchContent::Masked { value, mask } => {
+                let from: usize = (begin - self.offset).try_into().unwrap();
+                let to: usize = (end - self.offset).try_into().unwrap();
+                PatchContent::Masked {
+                    value: Arc::from(&value[from..to]),
+                    mask: Arc::from(&mask[from..to]),
+                }
+            }
+            PatchContent::Computed {
+                transform, input, ..
+            } => PatchContent::Computed {
+                transform: Arc::clone(transform),
+                input: input.clone(),
+                len: end - begin,
+            },
+        };
+        Some(Self {
+            offset: begin,
+            content,
+            metadata: self.metadata.clone(),
+        })
+    }
+
+    /// reads the bytes of this patch, starting at `offset` bytes after the
+    /// beginning of this patch. Patches which refer to other data of their
+    /// overlay, or which depend on the bytes beneath them, cannot be read
+    /// directly.
     pub fn read(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
-        let mut cursor = Cursor::new(&self.content);
-        cursor.seek(SeekFrom::Start(offset))?;
-        cursor.read(buf)
+        if offset >= self.len() {
+            return Ok(0);
+        }
+        let length: usize = min(buf.len() as u64, self.len() - offset).try_into().unwrap();
+        match &self.content {
+            PatchContent::Bytes(content) => {
+                let offset: usize = offset.try_into().unwrap();
+                buf[..length].copy_from_slice(&content[offset..offset + length]);
+            }
+            PatchContent::Fill { pattern, .. } => {
+                let shift = (offset % pattern.len() as u64) as usize;
+                for (dst, src) in buf[..length]
+                    .iter_mut()
+                    .zip(pattern.iter().cycle().skip(shift))
+                {
+                    *dst = *src;
+                }
+            }
+            PatchContent::External { source, range } => {
+                let mut source = source
+                    .lock()
+                    .map_err(|_| Error::other("the source of this patch is poisoned"))?;
+                source.seek(SeekFrom::Start(range.start + offset))?;
+                let mut bytes = 0;
+                while bytes < length {
+                    match source.read(&mut buf[bytes..length]) {
+                        Ok(0) => {
+                            return Err(Error::new(
+                                ErrorKind::UnexpectedEof,
+                                "the source of this patch is too short",
+                            ))
+                        }
+                        Ok(n) => bytes += n,
+                        Err(why) if why.kind() == ErrorKind::Interrupted => (),
+                        Err(why) => return Err(why),
+                    }
+                }
+            }
+            PatchContent::Reference { .. }
+            | PatchContent::Masked { .. }
+            | PatchContent::Computed { .. } => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "this patch refers to other data and must be read through its overlay",
+                ))
+            }
+        }
+        Ok(length)
     }
 }
 
@@ -107,6 +495,14 @@ impl Ord for Patch {
 
 impl Eq for Patch {}
 
+/// makes sure that the end of a patch can be represented
+fn check_end(offset: u64, len: u64) -> Result<(), OverlayError> {
+    match offset.checked_add(len) {
+        Some(_) => Ok(()),
+        None => Err(OverlayError::OffsetOverflow { offset, len }),
+    }
+}
+
 impl Contains for Patch {
     fn contains(&self, offset: u64) -> bool {
         self.begin() <= offset && offset < self.end()
@@ -118,9 +514,11 @@ impl SolidPatch<&[u8]> for Patch {
         if content.is_empty() {
             Err(OverlayError::EmptyPatch)
         } else {
+            check_end(offset, content.len() as u64)?;
             Ok(Self {
                 offset,
-                content: Vec::from(content),
+                content: PatchContent::Bytes(Arc::from(content)),
+                metadata: None,
             })
         }
     }
@@ -131,7 +529,12 @@ impl SolidPatch<Vec<u8>> for Patch {
         if content.is_empty() {
             Err(OverlayError::EmptyPatch)
         } else {
-            Ok(Self { offset, content })
+            check_end(offset, content.len() as u64)?;
+            Ok(Self {
+                offset,
+                content: PatchContent::Bytes(Arc::from(content)),
+                metadata: None,
+            })
         }
     }
 }
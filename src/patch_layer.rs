@@ -1,4 +1,4 @@
-use std::collections::BTreeSet;
+use std::{collections::BTreeSet, ops::Range};
 
 use crate::{Patch, Contains, PatchSearchResult};
 
@@ -8,6 +8,8 @@ pub struct PatchLayer {
 }
 
 impl PatchLayer {
+    // patches are ordered only by their position, which cannot be changed
+    #[allow(clippy::mutable_key_type)]
     pub fn new_with(patch: Patch) -> Self {
         let mut patches = BTreeSet::new();
         patches.insert(patch);
@@ -29,7 +31,7 @@ impl PatchLayer {
         self.patches.iter().find(|patch| patch.contains(offset))
     }
 
-    pub fn next_patch_for(&self, offset: u64) -> PatchSearchResult {
+    pub fn next_patch_for(&self, offset: u64) -> PatchSearchResult<'_> {
         let mut current_patch = None;
         for next_patch in self.patches.iter() {
             if let Some(current_patch) = current_patch {
@@ -50,6 +52,33 @@ impl PatchLayer {
         }
     }
 
+    /// removes all bytes in `range` from this layer. Patches which lie only
+    /// partially inside of `range` are shortened or split. Returns `true` if
+    /// the layer has been changed
+    pub fn remove_range(&mut self, range: &Range<u64>) -> bool {
+        let affected: Vec<_> = self
+            .patches
+            .iter()
+            .filter(|patch| patch.begin() < range.end && range.start < patch.end())
+            .cloned()
+            .collect();
+
+        for patch in affected.iter() {
+            self.patches.remove(patch);
+            if let Some(head) = patch.slice(patch.begin()..range.start) {
+                self.patches.insert(head);
+            }
+            if let Some(tail) = patch.slice(range.end..patch.end()) {
+                self.patches.insert(tail);
+            }
+        }
+        !affected.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
     pub fn iter_patches(&self) -> impl DoubleEndedIterator<Item=&Patch> {
         self.patches.iter()
     }
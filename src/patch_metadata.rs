@@ -0,0 +1,86 @@
+use std::{ops::Range, time::SystemTime};
+
+use crate::Patch;
+
+/// describes who created a patch, when and why, see
+/// [`crate::MemOverlay::set_metadata`]
+///
+/// # Example
+/// ```
+/// use memoverlay::PatchMetadata;
+///
+/// let metadata = PatchMetadata::new("alice")
+///     .with_reason("disable license check")
+///     .with_tag("crack");
+/// assert_eq!(metadata.author, "alice");
+/// assert!(metadata.timestamp.is_none());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PatchMetadata {
+    /// the time when the patch has been written. If this is `None` when the
+    /// patch is added to an overlay, the current time is used.
+    pub timestamp: Option<SystemTime>,
+    pub author: String,
+    pub reason: String,
+    pub tags: Vec<String>,
+}
+
+impl PatchMetadata {
+    pub fn new(author: impl Into<String>) -> Self {
+        Self {
+            author: author.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_reason(self, reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            ..self
+        }
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn with_timestamp(self, timestamp: SystemTime) -> Self {
+        Self {
+            timestamp: Some(timestamp),
+            ..self
+        }
+    }
+}
+
+/// where the visible bytes of a [`BlameEntry`] come from
+#[derive(Clone)]
+pub enum BlameSource {
+    /// the bytes are taken from the base
+    Base,
+
+    /// the bytes lie between the end of the base and the next patch, and
+    /// are zero
+    Gap,
+
+    /// the bytes are supplied by `patch`, which is part of the patch layer
+    /// with index `layer`. The topmost layer has the index 0.
+    Patch { layer: usize, patch: Patch },
+}
+
+impl BlameSource {
+    /// returns the metadata of the patch which supplies the bytes
+    pub fn metadata(&self) -> Option<&PatchMetadata> {
+        match self {
+            Self::Patch { patch, .. } => patch.metadata(),
+            _ => None,
+        }
+    }
+}
+
+/// a range of visible bytes, which come from the same source
+#[derive(Clone)]
+pub struct BlameEntry {
+    pub range: Range<u64>,
+    pub source: BlameSource,
+}
@@ -0,0 +1,125 @@
+use std::{collections::BTreeMap, ops::Range};
+
+/// a set of non-overlapping, non-adjacent ranges of offsets
+///
+/// # Example
+/// ```
+/// use memoverlay::RangeSet;
+///
+/// let mut set = RangeSet::default();
+/// set.insert(10..20);
+/// set.insert(15..30);
+/// set.remove(12..14);
+/// assert_eq!(set.iter().collect::<Vec<_>>(), vec![10..12, 14..30]);
+/// assert!(set.overlaps(&(25..40)));
+/// assert!(!set.overlaps(&(12..14)));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RangeSet {
+    ranges: BTreeMap<u64, u64>,
+}
+
+impl RangeSet {
+    /// adds a range to the set, merging it with overlapping or adjacent ranges
+    pub fn insert(&mut self, range: Range<u64>) {
+        if range.is_empty() {
+            return;
+        }
+        let mut begin = range.start;
+        let mut end = range.end;
+
+        let touching: Vec<_> = self
+            .ranges
+            .range(..=end)
+            .rev()
+            .take_while(|(_, &e)| e >= begin)
+            .map(|(&b, &e)| (b, e))
+            .collect();
+        for (b, e) in touching {
+            self.ranges.remove(&b);
+            begin = begin.min(b);
+            end = end.max(e);
+        }
+        self.ranges.insert(begin, end);
+    }
+
+    /// removes all offsets in `range` from the set
+    pub fn remove(&mut self, range: Range<u64>) {
+        if range.is_empty() {
+            return;
+        }
+        let overlapping: Vec<_> = self.overlapping(&range).collect();
+        for r in overlapping {
+            self.ranges.remove(&r.start);
+            if r.start < range.start {
+                self.ranges.insert(r.start, range.start);
+            }
+            if range.end < r.end {
+                self.ranges.insert(range.end, r.end);
+            }
+        }
+    }
+
+    pub fn contains(&self, offset: u64) -> bool {
+        self.ranges
+            .range(..=offset)
+            .next_back()
+            .is_some_and(|(_, &end)| offset < end)
+    }
+
+    pub fn overlaps(&self, range: &Range<u64>) -> bool {
+        self.overlapping(range).next().is_some()
+    }
+
+    /// returns all ranges of the set which overlap `range`, in ascending order
+    pub fn overlapping<'a>(&'a self, range: &Range<u64>) -> impl Iterator<Item = Range<u64>> + 'a {
+        let start = range.start;
+        let first = self
+            .ranges
+            .range(..=start)
+            .next_back()
+            .filter(|(_, &end)| end > start)
+            .map(|(&begin, _)| begin)
+            .unwrap_or(start);
+        let end = range.end.max(first);
+        let is_empty = range.is_empty();
+        self.ranges
+            .range(first..end)
+            .map(|(&b, &e)| b..e)
+            .filter(move |r| !is_empty && r.end > start)
+    }
+
+    /// returns the parts of `range` which are not contained in the set, in
+    /// ascending order
+    pub fn gaps(&self, range: &Range<u64>) -> Vec<Range<u64>> {
+        let mut gaps = Vec::new();
+        let mut pos = range.start;
+        for r in self.overlapping(range) {
+            if r.start > pos {
+                gaps.push(pos..r.start);
+            }
+            pos = pos.max(r.end);
+        }
+        if pos < range.end {
+            gaps.push(pos..range.end);
+        }
+        gaps
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+        self.ranges.iter().map(|(&b, &e)| b..e)
+    }
+
+    /// returns the number of offsets contained in the set
+    pub fn len(&self) -> u64 {
+        self.ranges.iter().map(|(b, e)| e - b).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear()
+    }
+}
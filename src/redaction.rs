@@ -0,0 +1,181 @@
+use std::{
+    fmt::Display,
+    io::{Read, Result, Seek},
+    ops::Range,
+};
+
+use crate::{MemOverlay, OverlayError, SearchPattern};
+
+/// what a [`RedactionRule`] redacts
+#[derive(Clone, Debug)]
+pub enum RedactionTarget {
+    /// a fixed range of offsets
+    Range(Range<u64>),
+
+    /// all matches of a pattern
+    Pattern(SearchPattern),
+}
+
+/// a named rule which describes data which must be redacted
+#[derive(Clone, Debug)]
+pub struct RedactionRule {
+    pub name: String,
+    pub target: RedactionTarget,
+}
+
+impl RedactionRule {
+    pub fn range(name: impl Into<String>, range: Range<u64>) -> Self {
+        Self {
+            name: name.into(),
+            target: RedactionTarget::Range(range),
+        }
+    }
+
+    pub fn pattern(name: impl Into<String>, pattern: SearchPattern) -> Self {
+        Self {
+            name: name.into(),
+            target: RedactionTarget::Pattern(pattern),
+        }
+    }
+
+    /// a rule which matches email addresses
+    #[cfg(feature = "regex")]
+    pub fn email_addresses() -> Self {
+        let pattern = SearchPattern::regex(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}")
+            .unwrap()
+            .with_max_len(320);
+        Self::pattern("email address", pattern)
+    }
+
+    /// a rule which matches payment card numbers with 13 to 19 digits,
+    /// which may be grouped by spaces or dashes
+    #[cfg(feature = "regex")]
+    pub fn card_numbers() -> Self {
+        let pattern = SearchPattern::regex(r"\b\d{4}(?:[ -]?\d){9,15}\b")
+            .unwrap()
+            .with_max_len(64);
+        Self::pattern("card number", pattern)
+    }
+}
+
+/// a single redacted range. It does not contain the redacted data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedactionEntry {
+    pub offset: u64,
+    pub len: u64,
+    pub rule: String,
+}
+
+/// lists all ranges which have been redacted, ordered by their offset
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RedactionReport {
+    pub entries: Vec<RedactionEntry>,
+}
+
+impl RedactionReport {
+    /// returns the total number of redacted bytes. Bytes which are matched by
+    /// more than one rule are counted multiple times.
+    pub fn redacted_bytes(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.len).sum()
+    }
+}
+
+impl Display for RedactionReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:>18} {:>12}  rule", "offset", "length")?;
+        for entry in self.entries.iter() {
+            writeln!(f, "{:#018x} {:>12}  {}", entry.offset, entry.len, entry.rule)?;
+        }
+        Ok(())
+    }
+}
+
+/// overwrites sensitive data with a fill pattern, using fill patches (see
+/// [`crate::Patch::fill`]).
+///
+/// The redaction is applied atomically. If [`Redactor::apply`] is called
+/// inside of a transaction (see [`MemOverlay::begin`]), the result can be
+/// reviewed before it is committed or aborted.
+///
+/// # Example
+/// ```
+/// use std::io::{Cursor, Read};
+/// use memoverlay::{MemOverlay, Redactor, RedactionRule, SearchPattern};
+///
+/// let mut overlay = MemOverlay::from(Cursor::new(b"user=alice;pass=secret;"));
+/// let mut redactor = Redactor::new("*").unwrap();
+/// redactor.add_rule(RedactionRule::range("user", 5..10));
+/// redactor.add_rule(RedactionRule::pattern("password", SearchPattern::bytes("secret").unwrap()));
+///
+/// let report = redactor.apply(&mut overlay).unwrap();
+/// assert_eq!(report.entries.len(), 2);
+/// assert_eq!(report.entries[1].offset, 16);
+///
+/// let mut data = String::new();
+/// overlay.read_to_string(&mut data).unwrap();
+/// assert_eq!(data, "user=*****;pass=******;");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Redactor {
+    rules: Vec<RedactionRule>,
+    fill: Vec<u8>,
+}
+
+impl Redactor {
+    /// creates a redactor which overwrites data by repeating `fill`
+    pub fn new(fill: impl AsRef<[u8]>) -> std::result::Result<Self, OverlayError> {
+        let fill = fill.as_ref();
+        if fill.is_empty() {
+            Err(OverlayError::EmptyPatch)
+        } else {
+            Ok(Self {
+                rules: Vec::new(),
+                fill: fill.to_vec(),
+            })
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: RedactionRule) {
+        self.rules.push(rule)
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &RedactionRule> {
+        self.rules.iter()
+    }
+
+    /// finds everything which would be redacted, without changing any data
+    pub fn plan<R: Read + Seek>(&self, overlay: &mut MemOverlay<R>) -> Result<RedactionReport> {
+        let mut entries = Vec::new();
+        for rule in self.rules.iter() {
+            let ranges = match &rule.target {
+                RedactionTarget::Range(range) => {
+                    let end = std::cmp::min(range.end, overlay.data_len());
+                    (range.start < end)
+                        .then_some(range.start..end)
+                        .into_iter()
+                        .collect()
+                }
+                RedactionTarget::Pattern(pattern) => overlay.find_all(pattern)?,
+            };
+            entries.extend(ranges.into_iter().map(|range| RedactionEntry {
+                offset: range.start,
+                len: range.end - range.start,
+                rule: rule.name.clone(),
+            }));
+        }
+        entries.sort_by_key(|entry| (entry.offset, entry.len));
+        Ok(RedactionReport { entries })
+    }
+
+    /// overwrites everything which is matched by any rule
+    pub fn apply<R: Read + Seek>(&self, overlay: &mut MemOverlay<R>) -> Result<RedactionReport> {
+        let report = self.plan(overlay)?;
+        overlay.atomically(|overlay| {
+            for entry in report.entries.iter() {
+                overlay.fill_at(entry.offset, &self.fill, entry.len)?;
+            }
+            Ok::<_, std::io::Error>(())
+        })?;
+        Ok(report)
+    }
+}
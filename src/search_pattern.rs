@@ -0,0 +1,131 @@
+use std::ops::Range;
+
+use crate::OverlayError;
+
+/// default number of bytes a regular expression match may span, see
+/// [`SearchPattern::max_len`]
+#[cfg(feature = "regex")]
+pub const DEFAULT_REGEX_MAX_LEN: usize = 4096;
+
+/// something to search for in a [`crate::MemOverlay`]
+///
+/// # Example
+/// ```
+/// use memoverlay::SearchPattern;
+///
+/// let pattern = SearchPattern::hex("4d 5a ?? 00").unwrap();
+/// assert_eq!(pattern.find_from(b"xxMZ\x90\x00", 0), Some(2..6));
+/// assert!(SearchPattern::hex("4d 5").is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub enum SearchPattern {
+    /// an exact sequence of bytes
+    Bytes(Vec<u8>),
+
+    /// a sequence of bytes, where `None` matches any byte
+    Wildcard(Vec<Option<u8>>),
+
+    /// a regular expression. Matches may not be longer than `max_len` bytes
+    #[cfg(feature = "regex")]
+    Regex {
+        regex: regex::bytes::Regex,
+        max_len: usize,
+    },
+}
+
+impl SearchPattern {
+    pub fn bytes(bytes: impl AsRef<[u8]>) -> Result<Self, OverlayError> {
+        let bytes = bytes.as_ref();
+        if bytes.is_empty() {
+            Err(OverlayError::InvalidPattern("the pattern is empty".into()))
+        } else {
+            Ok(Self::Bytes(bytes.to_vec()))
+        }
+    }
+
+    /// parses a sequence of hexadecimal bytes, such as `"4d 5a ?? 00"` or
+    /// `"4d5a??00"`. `??` matches any byte.
+    pub fn hex(pattern: &str) -> Result<Self, OverlayError> {
+        let digits: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+        if digits.is_empty() || !digits.len().is_multiple_of(2) {
+            return Err(OverlayError::InvalidPattern(format!(
+                "'{pattern}' is not a sequence of hexadecimal bytes"
+            )));
+        }
+
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| {
+                let pair: String = pair.iter().collect();
+                if pair == "??" {
+                    Ok(None)
+                } else {
+                    u8::from_str_radix(&pair, 16).map(Some).map_err(|_| {
+                        OverlayError::InvalidPattern(format!("'{pair}' is not a hexadecimal byte"))
+                    })
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if bytes.iter().all(|b| b.is_some()) {
+            Ok(Self::Bytes(bytes.into_iter().flatten().collect()))
+        } else {
+            Ok(Self::Wildcard(bytes))
+        }
+    }
+
+    /// creates a pattern from a regular expression, which is matched against
+    /// bytes, see [`regex::bytes::Regex`]
+    #[cfg(feature = "regex")]
+    pub fn regex(regex: &str) -> Result<Self, OverlayError> {
+        let regex = regex::bytes::Regex::new(regex)
+            .map_err(|why| OverlayError::InvalidPattern(why.to_string()))?;
+        Ok(Self::Regex {
+            regex,
+            max_len: DEFAULT_REGEX_MAX_LEN,
+        })
+    }
+
+    /// sets the maximum length of a regular expression match. Matches which
+    /// are longer might be cut short or not be found if they span two search
+    /// chunks.
+    #[cfg(feature = "regex")]
+    pub fn with_max_len(self, max_len: usize) -> Self {
+        match self {
+            Self::Regex { regex, .. } => Self::Regex { regex, max_len },
+            other => other,
+        }
+    }
+
+    /// returns the maximum number of bytes a match can span
+    pub fn max_len(&self) -> usize {
+        match self {
+            Self::Bytes(bytes) => bytes.len(),
+            Self::Wildcard(bytes) => bytes.len(),
+            #[cfg(feature = "regex")]
+            Self::Regex { max_len, .. } => *max_len,
+        }
+    }
+
+    /// finds the first match in `haystack` which starts at or after `start`
+    pub fn find_from(&self, haystack: &[u8], start: usize) -> Option<Range<usize>> {
+        if start > haystack.len() {
+            return None;
+        }
+        match self {
+            Self::Bytes(bytes) => memchr::memmem::find(&haystack[start..], bytes)
+                .map(|pos| start + pos..start + pos + bytes.len()),
+            Self::Wildcard(bytes) => haystack[start..]
+                .windows(bytes.len())
+                .position(|window| {
+                    window
+                        .iter()
+                        .zip(bytes.iter())
+                        .all(|(b, p)| p.is_none_or(|p| p == *b))
+                })
+                .map(|pos| start + pos..start + pos + bytes.len()),
+            #[cfg(feature = "regex")]
+            Self::Regex { regex, .. } => regex.find_at(haystack, start).map(|m| m.range()),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    io::{Read, Result, Seek},
+    ops::Range,
+};
+
+use crate::{MemOverlay, OverlayError, SearchPattern};
+
+/// a patch which finds its target by a signature instead of a fixed offset,
+/// so that it can be applied to slightly different versions of the same
+/// data. The signature must match exactly once.
+///
+/// # Example
+/// ```
+/// use std::io::Cursor;
+/// use memoverlay::{MemOverlay, SearchPattern, SignaturePatch};
+///
+/// // jz +5, followed by a call with an unknown target
+/// let signature = SearchPattern::hex("74 05 e8 ?? ?? ?? ??").unwrap();
+/// let patch = SignaturePatch::new(signature, [0xeb]);
+///
+/// let mut overlay = MemOverlay::from(Cursor::new(b"\x90\x90\x74\x05\xe8\x01\x02\x03\x04\xc3"));
+/// assert_eq!(patch.apply(&mut overlay).unwrap(), 2);
+/// assert_eq!(overlay.read_u8_at(2).unwrap(), 0xeb);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SignaturePatch {
+    signature: SearchPattern,
+    window: Option<Range<u64>>,
+    delta: i64,
+    bytes: Vec<u8>,
+}
+
+impl SignaturePatch {
+    /// creates a patch which writes `bytes` at the beginning of the match of
+    /// `signature`
+    pub fn new(signature: SearchPattern, bytes: impl AsRef<[u8]>) -> Self {
+        Self {
+            signature,
+            window: None,
+            delta: 0,
+            bytes: bytes.as_ref().to_vec(),
+        }
+    }
+
+    /// writes the bytes `delta` bytes after the beginning of the match,
+    /// instead of at the beginning
+    pub fn with_delta(self, delta: i64) -> Self {
+        Self { delta, ..self }
+    }
+
+    /// searches only for matches which lie completely inside of `window`
+    pub fn with_window(self, window: Range<u64>) -> Self {
+        Self {
+            window: Some(window),
+            ..self
+        }
+    }
+
+    pub fn signature(&self) -> &SearchPattern {
+        &self.signature
+    }
+
+    pub fn window(&self) -> Option<&Range<u64>> {
+        self.window.as_ref()
+    }
+
+    pub fn delta(&self) -> i64 {
+        self.delta
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// returns the offset where the bytes would be written
+    pub fn locate<R: Read + Seek>(&self, overlay: &mut MemOverlay<R>) -> Result<u64> {
+        let window = self.window.clone().unwrap_or(0..overlay.data_len());
+        let mut offsets: Vec<_> = overlay
+            .find_in(&self.signature, window.clone())?
+            .into_iter()
+            .map(|found| found.start)
+            .collect();
+
+        // matches are not allowed to overlap each other, so there might be
+        // another match which overlaps the only match that has been found
+        if let [offset] = offsets[..] {
+            let others = overlay.find_in(&self.signature, offset + 1..window.end)?;
+            offsets.extend(others.into_iter().map(|found| found.start));
+        }
+
+        match offsets[..] {
+            [] => Err(OverlayError::SignatureNotFound.into()),
+            [offset] => offset.checked_add_signed(self.delta).ok_or_else(|| {
+                OverlayError::TargetOutOfRange {
+                    offset,
+                    delta: self.delta,
+                }
+                .into()
+            }),
+            _ => Err(OverlayError::AmbiguousSignature { offsets }.into()),
+        }
+    }
+
+    /// locates the target and writes the bytes there. Returns the offset of
+    /// the target
+    pub fn apply<R: Read + Seek>(&self, overlay: &mut MemOverlay<R>) -> Result<u64> {
+        let offset = self.locate(overlay)?;
+        overlay.add_bytes_at(offset, &self.bytes)?;
+        Ok(offset)
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    cmp::min,
+    fs::File,
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
+};
+
+/// default number of bytes which are kept in memory before the buffer is
+/// moved to a temporary file
+pub const DEFAULT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+enum Storage {
+    Memory(Vec<u8>),
+    File(File),
+}
+
+/// makes a forward-only reader seekable by buffering everything that has been
+/// read from it. The buffer is kept in memory until it reaches a configurable
+/// limit, after that it is moved into a temporary file.
+///
+/// The length of the underlying stream is not known until its end has been
+/// reached. Seeking relative to the end reads the whole stream.
+///
+/// # Example
+/// ```
+/// use std::io::{Read, Seek, SeekFrom};
+/// use memoverlay::StreamBase;
+///
+/// let mut base = StreamBase::new(&b"hello, world!"[..]);
+/// assert_eq!(base.known_len(), None);
+///
+/// let mut buf = [0; 5];
+/// base.seek(SeekFrom::Start(7)).unwrap();
+/// base.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"world");
+///
+/// base.seek(SeekFrom::Start(0)).unwrap();
+/// base.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"hello");
+///
+/// assert_eq!(base.seek(SeekFrom::End(0)).unwrap(), 13);
+/// assert_eq!(base.known_len(), Some(13));
+/// ```
+pub struct StreamBase<R: Read> {
+    inner: R,
+    storage: Storage,
+    buffered: u64,
+    memory_limit: usize,
+    pos: u64,
+    eof: bool,
+}
+
+impl<R> StreamBase<R>
+where
+    R: Read,
+{
+    pub fn new(inner: R) -> Self {
+        Self::with_memory_limit(inner, DEFAULT_MEMORY_LIMIT)
+    }
+
+    /// creates a new buffer which moves its content into a temporary file
+    /// as soon as more than `memory_limit` bytes have been read
+    pub fn with_memory_limit(inner: R, memory_limit: usize) -> Self {
+        Self {
+            inner,
+            storage: Storage::Memory(Vec::new()),
+            buffered: 0,
+            memory_limit,
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// returns the length of the underlying stream, or `None` if the end of
+    /// the stream has not been reached yet
+    pub fn known_len(&self) -> Option<u64> {
+        if self.eof {
+            Some(self.buffered)
+        } else {
+            None
+        }
+    }
+
+    /// returns the number of bytes which have been read from the underlying
+    /// stream so far
+    pub fn buffered_len(&self) -> u64 {
+        self.buffered
+    }
+
+    /// returns `true` if the buffer has been moved into a temporary file
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.storage, Storage::File(_))
+    }
+
+    /// reads from the underlying stream until at least `target` bytes are
+    /// buffered or the end of the stream has been reached
+    fn fill_to(&mut self, target: u64) -> Result<()> {
+        let mut chunk = vec![0; READ_CHUNK_SIZE];
+        while !self.eof && self.buffered < target {
+            let bytes = match self.inner.read(&mut chunk) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(bytes) => bytes,
+                Err(why) if why.kind() == ErrorKind::Interrupted => continue,
+                Err(why) => return Err(why),
+            };
+            self.append(&chunk[..bytes])?;
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<()> {
+        match &mut self.storage {
+            Storage::Memory(buffer) => {
+                buffer.extend_from_slice(data);
+                if buffer.len() > self.memory_limit {
+                    let mut file = tempfile::tempfile()?;
+                    file.write_all(buffer)?;
+                    self.storage = Storage::File(file);
+                }
+            }
+            Storage::File(file) => {
+                file.seek(SeekFrom::End(0))?;
+                file.write_all(data)?;
+            }
+        }
+        self.buffered += data.len() as u64;
+        Ok(())
+    }
+}
+
+impl<R> Read for StreamBase<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.fill_to(self.pos.saturating_add(buf.len() as u64))?;
+        if self.pos >= self.buffered {
+            return Ok(0);
+        }
+
+        let length: usize = min(buf.len() as u64, self.buffered - self.pos)
+            .try_into()
+            .unwrap();
+        let bytes = match &mut self.storage {
+            Storage::Memory(buffer) => {
+                let start: usize = self.pos.try_into().unwrap();
+                buf[..length].copy_from_slice(&buffer[start..start + length]);
+                length
+            }
+            Storage::File(file) => {
+                file.seek(SeekFrom::Start(self.pos))?;
+                file.read(&mut buf[..length])?
+            }
+        };
+        self.pos += bytes as u64;
+        Ok(bytes)
+    }
+}
+
+impl<R> Seek for StreamBase<R>
+where
+    R: Read,
+{
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(diff) => self.pos.checked_add_signed(diff),
+            SeekFrom::End(diff) => {
+                self.fill_to(u64::MAX)?;
+                self.buffered.checked_add_signed(diff)
+            }
+        };
+
+        match new_pos {
+            Some(new_pos) => {
+                self.pos = new_pos;
+                Ok(new_pos)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "cannot seek before start of stream",
+            )),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::OverlayError;
+
+/// the character encoding of a stored string
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextEncoding {
+    Ascii,
+    Utf8,
+    Utf16Le,
+}
+
+/// the width of a length prefix. The prefix is stored in little endian byte
+/// order and counts code units, which are bytes for [`TextEncoding::Ascii`]
+/// and [`TextEncoding::Utf8`], and 16 bit words for [`TextEncoding::Utf16Le`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthPrefix {
+    U8,
+    U16Le,
+    U32Le,
+}
+
+/// how a string is laid out in the data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StringLayout {
+    /// the string occupies exactly `width` bytes
+    Fixed { width: usize },
+
+    /// the string is preceded by its length, and may occupy at most
+    /// `capacity` bytes after the prefix
+    LengthPrefixed {
+        prefix: LengthPrefix,
+        capacity: usize,
+    },
+}
+
+/// what happens if a string needs more bytes than its layout provides
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TooLong {
+    /// the string is rejected
+    #[default]
+    Reject,
+
+    /// the string is truncated at a character boundary
+    Truncate,
+}
+
+/// what happens if a string needs fewer bytes than its layout provides
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TooShort {
+    /// the remaining bytes are filled with NUL bytes
+    #[default]
+    Pad,
+
+    /// the string is rejected
+    Reject,
+}
+
+/// what happens if a string does not fit exactly into its layout. Both
+/// choices mean the same for fixed width and for length prefixed strings,
+/// whose space is the capacity behind the prefix.
+///
+/// The default policy pads shorter strings and rejects longer strings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FitPolicy {
+    pub too_long: TooLong,
+    pub too_short: TooShort,
+}
+
+/// describes how a string is encoded, see
+/// [`crate::MemOverlay::write_str_at`] and [`crate::MemOverlay::read_str_at`]
+///
+/// # Example
+/// ```
+/// use std::io::Cursor;
+/// use memoverlay::{FitPolicy, MemOverlay, StringFormat, TooLong};
+///
+/// let mut overlay = MemOverlay::from(Cursor::new([0xffu8; 16]));
+/// let format = StringFormat::utf16le(8).with_fit(FitPolicy {
+///     too_long: TooLong::Truncate,
+///     ..Default::default()
+/// });
+/// overlay.write_str_at(0, "hello", &format).unwrap();
+/// assert_eq!(overlay.read_str_at(0, &format).unwrap(), "hell");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StringFormat {
+    pub encoding: TextEncoding,
+    pub layout: StringLayout,
+    pub fit: FitPolicy,
+}
+
+impl StringFormat {
+    /// a NUL padded UTF-16LE string which occupies `width` bytes
+    pub fn utf16le(width: usize) -> Self {
+        Self {
+            encoding: TextEncoding::Utf16Le,
+            layout: StringLayout::Fixed { width },
+            fit: FitPolicy::default(),
+        }
+    }
+
+    /// a NUL padded ASCII string which occupies `width` bytes
+    pub fn ascii(width: usize) -> Self {
+        Self {
+            encoding: TextEncoding::Ascii,
+            layout: StringLayout::Fixed { width },
+            fit: FitPolicy::default(),
+        }
+    }
+
+    /// a string which is preceded by its length
+    pub fn length_prefixed(encoding: TextEncoding, prefix: LengthPrefix, capacity: usize) -> Self {
+        Self {
+            encoding,
+            layout: StringLayout::LengthPrefixed { prefix, capacity },
+            fit: FitPolicy::default(),
+        }
+    }
+
+    pub fn with_fit(self, fit: FitPolicy) -> Self {
+        Self { fit, ..self }
+    }
+
+    fn unit_size(&self) -> usize {
+        match self.encoding {
+            TextEncoding::Ascii | TextEncoding::Utf8 => 1,
+            TextEncoding::Utf16Le => 2,
+        }
+    }
+
+    /// encodes `s` into the bytes which must be written
+    pub fn encode(&self, s: &str) -> Result<Vec<u8>, OverlayError> {
+        let mut units = Vec::new();
+        for c in s.chars() {
+            let mut encoded = [0; 4];
+            let bytes: Vec<u8> = match self.encoding {
+                TextEncoding::Ascii if !c.is_ascii() => {
+                    return Err(OverlayError::UnencodableCharacter(c))
+                }
+                TextEncoding::Ascii | TextEncoding::Utf8 => {
+                    c.encode_utf8(&mut encoded).as_bytes().to_vec()
+                }
+                TextEncoding::Utf16Le => c
+                    .encode_utf16(&mut [0; 2])
+                    .iter()
+                    .flat_map(|unit| unit.to_le_bytes())
+                    .collect(),
+            };
+            units.push(bytes);
+        }
+
+        let capacity = match self.layout {
+            StringLayout::Fixed { width } => width,
+            StringLayout::LengthPrefixed { capacity, .. } => capacity,
+        };
+
+        // drop complete characters until the string fits
+        let mut length: usize = units.iter().map(|c| c.len()).sum();
+        if length > capacity {
+            if self.fit.too_long == TooLong::Reject {
+                return Err(OverlayError::StringTooLong {
+                    len: length,
+                    capacity,
+                });
+            }
+            while length > capacity {
+                length -= units.pop().unwrap().len();
+            }
+        }
+        if length < capacity && self.fit.too_short == TooShort::Reject {
+            return Err(OverlayError::StringTooShort {
+                len: length,
+                width: capacity,
+            });
+        }
+
+        let mut result = Vec::new();
+        if let StringLayout::LengthPrefixed { prefix, .. } = self.layout {
+            let count = (length / self.unit_size()) as u64;
+            let prefix_bytes = match prefix {
+                LengthPrefix::U8 => u8::try_from(count).map(|c| c.to_le_bytes().to_vec()).ok(),
+                LengthPrefix::U16Le => u16::try_from(count).map(|c| c.to_le_bytes().to_vec()).ok(),
+                LengthPrefix::U32Le => u32::try_from(count).map(|c| c.to_le_bytes().to_vec()).ok(),
+            };
+            match prefix_bytes {
+                Some(prefix_bytes) => result.extend(prefix_bytes),
+                None => {
+                    return Err(OverlayError::StringTooLong {
+                        len: length,
+                        capacity,
+                    })
+                }
+            }
+        }
+        result.extend(units.into_iter().flatten());
+        result.resize(self.prefix_size() + capacity, 0);
+        Ok(result)
+    }
+
+    /// returns the number of bytes occupied by the length prefix
+    pub fn prefix_size(&self) -> usize {
+        match self.layout {
+            StringLayout::Fixed { .. } => 0,
+            StringLayout::LengthPrefixed {
+                prefix: LengthPrefix::U8,
+                ..
+            } => 1,
+            StringLayout::LengthPrefixed {
+                prefix: LengthPrefix::U16Le,
+                ..
+            } => 2,
+            StringLayout::LengthPrefixed {
+                prefix: LengthPrefix::U32Le,
+                ..
+            } => 4,
+        }
+    }
+
+    /// returns the number of content bytes of a string with the given prefix
+    pub(crate) fn content_len(&self, prefix: &[u8]) -> Result<usize, OverlayError> {
+        match self.layout {
+            StringLayout::Fixed { width } => Ok(width),
+            StringLayout::LengthPrefixed { capacity, .. } => {
+                let mut count = [0; 8];
+                count[..prefix.len()].copy_from_slice(prefix);
+                let len = u64::from_le_bytes(count)
+                    .checked_mul(self.unit_size() as u64)
+                    .filter(|len| *len <= capacity as u64)
+                    .ok_or(OverlayError::InvalidString)?;
+                Ok(len as usize)
+            }
+        }
+    }
+
+    /// decodes the content bytes of a string. Fixed width strings end at the
+    /// first NUL character
+    pub fn decode(&self, content: &[u8]) -> Result<String, OverlayError> {
+        let is_fixed = matches!(self.layout, StringLayout::Fixed { .. });
+        match self.encoding {
+            TextEncoding::Ascii | TextEncoding::Utf8 => {
+                let end = if is_fixed {
+                    content
+                        .iter()
+                        .position(|b| *b == 0)
+                        .unwrap_or(content.len())
+                } else {
+                    content.len()
+                };
+                let content = &content[..end];
+                if self.encoding == TextEncoding::Ascii && !content.is_ascii() {
+                    return Err(OverlayError::InvalidString);
+                }
+                String::from_utf8(content.to_vec()).map_err(|_| OverlayError::InvalidString)
+            }
+            TextEncoding::Utf16Le => {
+                let mut units: Vec<u16> = content
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .collect();
+                if is_fixed {
+                    if let Some(end) = units.iter().position(|u| *u == 0) {
+                        units.truncate(end);
+                    }
+                }
+                String::from_utf16(&units).map_err(|_| OverlayError::InvalidString)
+            }
+        }
+    }
+}
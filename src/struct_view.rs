@@ -0,0 +1,94 @@
+use std::{
+    io::{Error, ErrorKind, Read, Result, Seek},
+    marker::PhantomData,
+};
+
+use zerocopy::{FromBytes, Immutable, IntoBytes};
+
+use crate::MemOverlay;
+
+/// gives typed access to a struct which is stored at some offset of a
+/// [`MemOverlay`]. The layout of the struct is defined using the derive
+/// macros of [`zerocopy`]. Changing the struct only creates patches for
+/// those bytes which actually change.
+///
+/// # Example
+/// ```
+/// use std::io::Cursor;
+/// use memoverlay::MemOverlay;
+/// use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+/// use zerocopy::little_endian::{U16, U32};
+///
+/// #[derive(FromBytes, IntoBytes, Immutable, KnownLayout)]
+/// #[repr(C)]
+/// struct Header {
+///     magic: [u8; 2],
+///     version: U16,
+///     size: U32,
+/// }
+///
+/// let mut overlay = MemOverlay::from(Cursor::new(*b"MZ\x01\x00\x00\x10\x00\x00"));
+/// let mut header = overlay.view::<Header>(0);
+/// assert_eq!(header.get().unwrap().version.get(), 1);
+///
+/// header.update(|h| h.version.set(2)).unwrap();
+/// assert_eq!(header.get().unwrap().size.get(), 0x1000);
+/// assert_eq!(overlay.patch_bytes(), 1);
+/// ```
+pub struct StructView<'a, R, T>
+where
+    R: Read + Seek,
+{
+    overlay: &'a mut MemOverlay<R>,
+    offset: u64,
+    phantom: PhantomData<T>,
+}
+
+impl<R> MemOverlay<R>
+where
+    R: Read + Seek,
+{
+    /// returns a typed view of the struct at `offset`
+    pub fn view<T>(&mut self, offset: u64) -> StructView<'_, R, T>
+    where
+        T: FromBytes + IntoBytes + Immutable,
+    {
+        StructView {
+            overlay: self,
+            offset,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<R, T> StructView<'_, R, T>
+where
+    R: Read + Seek,
+    T: FromBytes + IntoBytes + Immutable,
+{
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// reads the struct from the visible bytes
+    pub fn get(&mut self) -> Result<T> {
+        let mut buf = vec![0; std::mem::size_of::<T>()];
+        self.overlay.read_exact_at(self.offset, &mut buf)?;
+        T::read_from_bytes(&buf)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid struct size"))
+    }
+
+    /// writes the whole struct. Returns the number of bytes which have been
+    /// changed.
+    pub fn set(&mut self, value: &T) -> Result<usize> {
+        self.overlay.update_bytes_at(self.offset, value.as_bytes())
+    }
+
+    /// reads the struct, lets `f` modify it and writes it back. Returns the
+    /// number of bytes which have been changed.
+    pub fn update(&mut self, f: impl FnOnce(&mut T)) -> Result<usize> {
+        let mut value = self.get()?;
+        f(&mut value);
+        self.set(&value)
+    }
+}
@@ -0,0 +1,65 @@
+/// decides what happens to writes and reverts which touch a protected
+/// region
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProtectionMode {
+    /// the whole write fails
+    #[default]
+    Reject,
+
+    /// only the bytes outside of protected regions are written. A write
+    /// which lies completely inside of protected regions fails.
+    Clip,
+}
+
+/// configures which writes are accepted by a [`crate::MemOverlay`]. Writes
+/// which violate the policy fail with an [`crate::OverlayError`], which is
+/// wrapped into a [`std::io::Error`].
+///
+/// The default policy accepts every write which does not leave a gap behind
+/// the end of the data.
+///
+/// # Example
+/// ```
+/// use std::io::Cursor;
+/// use memoverlay::{MemOverlay, WritePolicy};
+///
+/// let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+/// overlay.set_write_policy(WritePolicy {
+///     allow_overlap: false,
+///     ..Default::default()
+/// });
+///
+/// assert!(overlay.add_bytes_at(7, "peter").is_ok());
+/// assert!(overlay.add_bytes_at(9, "x").is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WritePolicy {
+    /// accept writes which overlap bytes that have already been patched
+    pub allow_overlap: bool,
+
+    /// accept writes which reach beyond the end of the base
+    pub allow_growth: bool,
+
+    /// accept writes which start behind the end of the data, leaving a gap
+    /// which will be read as zeros
+    pub allow_gap: bool,
+
+    /// the maximum number of bytes which can be stored in patches
+    pub max_patch_bytes: Option<u64>,
+
+    /// how to handle writes to protected regions, see
+    /// [`crate::MemOverlay::protect`]
+    pub protection_mode: ProtectionMode,
+}
+
+impl Default for WritePolicy {
+    fn default() -> Self {
+        Self {
+            allow_overlap: true,
+            allow_growth: true,
+            allow_gap: false,
+            max_patch_bytes: None,
+            protection_mode: ProtectionMode::default(),
+        }
+    }
+}
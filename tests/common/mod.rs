@@ -0,0 +1,19 @@
+//! helpers which are shared by the integration tests. Not every test uses
+//! every helper.
+#![allow(dead_code)]
+
+use memoverlay::{MemOverlay, OverlayError};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// unwraps the [`OverlayError`] which is wrapped into `err`
+pub fn overlay_error(err: io::Error) -> OverlayError {
+    *err.into_inner().unwrap().downcast::<OverlayError>().unwrap()
+}
+
+/// reads all visible bytes of `overlay`, starting at its beginning
+pub fn read_all<R: Read + Seek>(overlay: &mut MemOverlay<R>) -> Vec<u8> {
+    let mut output = Vec::new();
+    overlay.seek(SeekFrom::Start(0)).unwrap();
+    overlay.read_to_end(&mut output).unwrap();
+    output
+}
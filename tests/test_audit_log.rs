@@ -0,0 +1,255 @@
+#![cfg(feature = "audit")]
+
+mod common;
+
+use common::overlay_error;
+use memoverlay::{
+    AuditLog, AuditRecord, ChangeBytes, MemOverlay, OverlayError, PatchMetadata, XorEncoding, MAX_EVENT_BYTES,
+};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn verification_failure(log: &AuditLog) -> u64 {
+    match log.verify() {
+        Err(OverlayError::AuditVerificationFailed { sequence, .. }) => sequence,
+        result => panic!("unexpected result {result:?}"),
+    }
+}
+
+/// test that all kinds of changes are recorded and can be replayed
+#[test]
+fn test_record_and_replay() {
+    let base = b"hello, world!".to_vec();
+    let mut overlay = MemOverlay::from(Cursor::new(base.clone()));
+    let log = AuditLog::attach(&mut overlay).unwrap();
+
+    overlay.set_metadata(Some(PatchMetadata::new("alice").with_reason("greet peter")));
+    overlay.add_bytes_at(7, "peter").unwrap();
+    overlay.add_bytes_at(13, " how are you?").unwrap();
+    overlay.revert(20..26).unwrap();
+    overlay.compact().unwrap();
+
+    let log = log.lock().unwrap().clone();
+    log.verify().unwrap();
+    // the base, two writes, a revert and a compaction of both patched ranges
+    assert_eq!(log.entries().count(), 6);
+    assert!(matches!(
+        log.entries().next().unwrap().record,
+        AuditRecord::Base { len: 13, .. }
+    ));
+    match &log.entries().nth(1).unwrap().record {
+        AuditRecord::Change(event) => assert_eq!(event.metadata.as_ref().unwrap().author, "alice"),
+        record => panic!("unexpected record {record:?}"),
+    }
+
+    let mut replayed = log.replay(Cursor::new(base)).unwrap();
+    let len = overlay.data_len();
+    assert_eq!(replayed.data_len(), len);
+    assert_eq!(
+        replayed.read_range(&(0..len)).unwrap(),
+        overlay.read_range(&(0..len)).unwrap()
+    );
+    assert_eq!(
+        replayed.read_range(&(0..len)).unwrap(),
+        b"hello, peter! how ar"
+    );
+}
+
+/// test that the base is fingerprinted as it is stored, and that an
+/// existing encoding and existing patches are recorded
+#[test]
+fn test_attach_later() {
+    let stored = vec![b'h' ^ 0x20, b'i' ^ 0x20, b'!' ^ 0x20];
+    let mut overlay = MemOverlay::from(Cursor::new(stored.clone()));
+    overlay.set_base_encoding(XorEncoding::new([0x20])).unwrap();
+    overlay.add_bytes_at(3, "?").unwrap();
+    let log = AuditLog::attach(&mut overlay).unwrap();
+    overlay.add_bytes_at(0, "H").unwrap();
+
+    let log = log.lock().unwrap().clone();
+    let expected: [u8; 32] = Sha256::digest(&stored).into();
+    assert_eq!(
+        log.entries().next().unwrap().record,
+        AuditRecord::Base {
+            len: 3,
+            sha256: expected
+        }
+    );
+    assert_eq!(log.entries().count(), 4);
+
+    let mut replayed = log.replay(Cursor::new(stored)).unwrap();
+    assert_eq!(replayed.read_range(&(0..4)).unwrap(), b"Hi!?");
+    assert!(log.replay(Cursor::new(b"hi!".to_vec())).is_err());
+}
+
+/// test that changes are still recorded after a thread panicked while it
+/// held the lock of the log
+#[test]
+fn test_poisoned_lock() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello".to_vec()));
+    let log = AuditLog::attach(&mut overlay).unwrap();
+    let poisoner = std::sync::Arc::clone(&log);
+    std::thread::spawn(move || {
+        let _log = poisoner.lock().unwrap();
+        panic!("poison the lock");
+    })
+    .join()
+    .unwrap_err();
+
+    overlay.add_bytes_at(0, "H").unwrap();
+    let log = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    assert_eq!(log.entries().count(), 2);
+    log.verify().unwrap();
+}
+
+/// test that large writes and a large encoded base are recorded with all of
+/// their bytes, so that they can be replayed
+#[test]
+fn test_large_write() {
+    let len = MAX_EVENT_BYTES as usize + 1000;
+    let stored: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+    let mut overlay = MemOverlay::from(Cursor::new(stored.clone()));
+    overlay.set_base_encoding(XorEncoding::new([0x20])).unwrap();
+    let log = AuditLog::attach(&mut overlay).unwrap();
+
+    let bytes: Vec<u8> = (0..len + 5).map(|i| (i % 13) as u8).collect();
+    overlay.add_bytes_at(3, &bytes).unwrap();
+    overlay.revert(0..10).unwrap();
+
+    let log = AuditLog::from_bytes(&log.lock().unwrap().to_bytes()).unwrap();
+    log.verify().unwrap();
+    let mut replayed = log.replay(Cursor::new(stored)).unwrap();
+    assert_eq!(replayed.data_len(), overlay.data_len());
+    assert_eq!(replayed.hash_view().unwrap(), overlay.hash_view().unwrap());
+}
+
+/// test that large fills are recorded and replayed without their bytes
+#[test]
+fn test_large_fill() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello".to_vec()));
+    let log = AuditLog::attach(&mut overlay).unwrap();
+    overlay.fill_at(2, "xy", MAX_EVENT_BYTES + 1).unwrap();
+    overlay.add_bytes_at(4, "!").unwrap();
+
+    let log = AuditLog::from_bytes(&log.lock().unwrap().to_bytes()).unwrap();
+    let Some(AuditRecord::Change(event)) = log.entries().nth(1).map(|entry| &entry.record) else {
+        panic!("the fill has not been recorded");
+    };
+    assert_eq!(event.new_bytes, ChangeBytes::Fill { pattern: b"xy".to_vec() });
+
+    let mut replayed = log.replay(Cursor::new(b"hello".to_vec())).unwrap();
+    assert_eq!(replayed.data_len(), MAX_EVENT_BYTES + 3);
+    assert_eq!(replayed.hash_view().unwrap(), overlay.hash_view().unwrap());
+}
+
+/// test that modified, removed and reordered entries are detected
+#[test]
+fn test_tampering() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!".to_vec()));
+    let log = AuditLog::attach(&mut overlay).unwrap();
+    overlay.add_bytes_at(0, "H").unwrap();
+    overlay.add_bytes_at(7, "W").unwrap();
+    overlay.add_bytes_at(12, "?").unwrap();
+    let entries: Vec<_> = log.lock().unwrap().entries().cloned().collect();
+
+    let mut reordered = entries.clone();
+    reordered.swap(1, 2);
+    assert_eq!(verification_failure(&AuditLog::from_entries(reordered)), 1);
+
+    let mut removed = entries.clone();
+    removed.remove(2);
+    assert_eq!(verification_failure(&AuditLog::from_entries(removed)), 2);
+
+    let mut modified = entries.clone();
+    if let AuditRecord::Change(event) = &mut modified[3].record {
+        event.new_bytes = ChangeBytes::Bytes(b"!".to_vec());
+    }
+    assert_eq!(verification_failure(&AuditLog::from_entries(modified)), 3);
+
+    // a different base is detected while replaying
+    let log = AuditLog::from_entries(entries);
+    log.verify().unwrap();
+    let err = log
+        .replay(Cursor::new(b"hello, World!".to_vec()))
+        .unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(
+        err,
+        OverlayError::AuditVerificationFailed { sequence: 0, .. }
+    ));
+}
+
+/// test that a log survives serialization
+#[test]
+fn test_serialization() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!".to_vec()));
+    let log = AuditLog::attach(&mut overlay).unwrap();
+    overlay.set_metadata(Some(PatchMetadata::new("bob").with_tag("a").with_tag("b")));
+    overlay.add_bytes_at(7, "peter").unwrap();
+    overlay.set_metadata(None);
+    overlay.add_bytes_at(0, "H").unwrap();
+
+    let log = log.lock().unwrap().clone();
+    let mut bytes = log.to_bytes();
+    assert_eq!(AuditLog::from_bytes(&bytes).unwrap(), log);
+
+    bytes.pop();
+    assert!(matches!(
+        AuditLog::from_bytes(&bytes),
+        Err(OverlayError::InvalidAuditLog(_))
+    ));
+}
+
+/// test that invalid timestamps are rejected instead of causing a panic
+#[test]
+fn test_invalid_timestamp() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello".to_vec()));
+    let log = AuditLog::attach(&mut overlay).unwrap();
+    let timestamp = UNIX_EPOCH + Duration::new(0x0123_4567, 0x0765_4321);
+    overlay.set_metadata(Some(PatchMetadata::new("bob").with_timestamp(timestamp)));
+    overlay.add_bytes_at(0, "H").unwrap();
+
+    let bytes = log.lock().unwrap().to_bytes();
+    let mut encoded = 0x0123_4567u64.to_le_bytes().to_vec();
+    encoded.extend(0x0765_4321u32.to_le_bytes());
+    let position = bytes.windows(12).position(|window| window == encoded).unwrap();
+
+    for (secs, nanos) in [(u64::MAX, 0), (0, 1_000_000_000)] {
+        let mut bytes = bytes.clone();
+        bytes[position..position + 8].copy_from_slice(&secs.to_le_bytes());
+        bytes[position + 8..position + 12].copy_from_slice(&u32::to_le_bytes(nanos));
+        assert!(matches!(
+            AuditLog::from_bytes(&bytes),
+            Err(OverlayError::InvalidAuditLog(_))
+        ));
+    }
+}
+
+/// test that a signed head covers the whole chain
+#[cfg(feature = "signing")]
+#[test]
+fn test_signing() {
+    use ed25519_dalek::SigningKey;
+
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!".to_vec()));
+    let log = AuditLog::attach(&mut overlay).unwrap();
+    overlay.add_bytes_at(7, "peter").unwrap();
+
+    let signature = log.lock().unwrap().sign_head(&key).unwrap();
+    log.lock()
+        .unwrap()
+        .verify_signature(&key.verifying_key(), &signature)
+        .unwrap();
+
+    overlay.add_bytes_at(0, "H").unwrap();
+    let log = log.lock().unwrap();
+    assert!(log
+        .verify_signature(&key.verifying_key(), &signature)
+        .is_err());
+    let other = SigningKey::from_bytes(&[8; 32]);
+    assert!(log
+        .verify_signature(&other.verifying_key(), &log.sign_head(&key).unwrap())
+        .is_err());
+}
@@ -0,0 +1,93 @@
+use memoverlay::{BaseEncoding, ChangeEvent, ChangeKind, CopyMode, MemOverlay, Rc4Encoding, SearchPattern, XorEncoding};
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Mutex};
+
+fn xor_encoded(data: &[u8], key: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    XorEncoding::new(key).encode(0, &mut data);
+    data
+}
+
+/// test that patches are applied to the decoded view, and are encoded again
+/// when exporting
+#[test]
+fn test_xor_roundtrip() {
+    let mut overlay = MemOverlay::from(Cursor::new(xor_encoded(b"hello, world!", b"k3y")));
+    overlay.set_base_encoding(XorEncoding::new("k3y")).unwrap();
+    assert_eq!(overlay.find_all(&SearchPattern::bytes("world").unwrap()).unwrap(), vec![7..12]);
+
+    overlay.add_bytes_at(7, "peter").unwrap();
+    overlay.copy_within(0..5, 13, CopyMode::Snapshot).unwrap();
+    let mut data = String::new();
+    overlay.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "hello, peter!hello");
+
+    let mut exported = Vec::new();
+    assert_eq!(overlay.export(&mut exported).unwrap(), 18);
+    assert_eq!(exported, xor_encoded(b"hello, peter!hello", b"k3y"));
+}
+
+/// test that RC4 can be read at random offsets
+#[test]
+fn test_rc4_random_access() {
+    let plain: Vec<u8> = (0..(3 << 20) as u32).map(|i| (i % 251) as u8).collect();
+    let mut encoded = plain.clone();
+    Rc4Encoding::new("secret").unwrap().encode(0, &mut encoded);
+
+    let mut overlay = MemOverlay::from(Cursor::new(encoded.clone()));
+    overlay.set_base_encoding(Rc4Encoding::new("secret").unwrap()).unwrap();
+    for offset in [(5 << 19) + 3, 100, (1 << 20) - 2, 3 << 19] {
+        let range = offset..offset + 16;
+        assert_eq!(overlay.read_range(&range).unwrap(), &plain[offset as usize..][..16]);
+    }
+
+    overlay.clear_base_encoding().unwrap();
+    assert_eq!(overlay.read_range(&(100..116)).unwrap(), &encoded[100..116]);
+}
+
+/// test that observers are informed when the encoding changes
+#[test]
+fn test_encoding_events() {
+    let mut overlay = MemOverlay::from(Cursor::new(xor_encoded(b"hello", b"k")));
+    let events: Arc<Mutex<Vec<ChangeEvent>>> = Arc::default();
+    let recorder = Arc::clone(&events);
+    overlay.add_observer(move |event| recorder.lock().unwrap().push(event.clone()));
+
+    overlay.set_base_encoding(XorEncoding::new("k")).unwrap();
+    overlay.clear_base_encoding().unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].kind, ChangeKind::Encoding);
+    assert_eq!(events[0].range, 0..5);
+    assert_eq!(events[0].old_bytes, xor_encoded(b"hello", b"k"));
+    assert_eq!(events[0].new_bytes, b"hello");
+    assert_eq!(events[1].old_bytes, b"hello");
+}
+
+/// test that invalid RC4 keys are rejected
+#[test]
+fn test_rc4_key() {
+    assert!(Rc4Encoding::new("").is_err());
+    assert!(Rc4Encoding::new([0u8; 257]).is_err());
+    assert!(Rc4Encoding::new([0u8; 256]).is_ok());
+}
+
+/// test that aborting a transaction restores the previous encoding
+#[test]
+fn test_encoding_abort() {
+    let mut overlay = MemOverlay::from(Cursor::new(xor_encoded(b"hello", b"k")));
+    overlay.set_base_encoding(XorEncoding::new("k")).unwrap();
+
+    overlay.begin().unwrap();
+    overlay.clear_base_encoding().unwrap();
+    overlay.set_base_encoding(XorEncoding::new("x")).unwrap();
+    overlay.abort().unwrap();
+    assert_eq!(overlay.read_range(&(0..5)).unwrap(), b"hello");
+
+    overlay.begin().unwrap();
+    overlay.clear_base_encoding().unwrap();
+    overlay.abort().unwrap();
+    assert!(overlay.base_encoding().is_some());
+    assert_eq!(overlay.read_range(&(0..5)).unwrap(), b"hello");
+}
@@ -0,0 +1,114 @@
+use memoverlay::{BlameSource, MemOverlay, Patch, PatchMetadata, WritePolicy};
+use std::io::Cursor;
+use std::time::{Duration, SystemTime};
+
+fn describe(overlay: &MemOverlay<Cursor<&[u8]>>, range: std::ops::Range<u64>) -> Vec<(u64, u64, String)> {
+    overlay
+        .blame_range(range)
+        .into_iter()
+        .map(|entry| {
+            let source = match &entry.source {
+                BlameSource::Base => "base".to_string(),
+                BlameSource::Gap => "gap".to_string(),
+                BlameSource::Patch { layer, .. } => {
+                    format!("{}@{layer}", entry.source.metadata().map_or("-", |m| &m.author))
+                }
+            };
+            (entry.range.start, entry.range.end, source)
+        })
+        .collect()
+}
+
+/// test which author supplies which bytes
+#[test]
+fn test_blame_authors() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123456789"[..]));
+    overlay.add_bytes_at(0, "x").unwrap();
+    overlay.set_metadata(Some(PatchMetadata::new("alice")));
+    overlay.add_bytes_at(2, "aaaa").unwrap();
+    overlay.set_metadata(Some(PatchMetadata::new("bob").with_tag("review")));
+    overlay.add_bytes_at(4, "bbbb").unwrap();
+
+    assert_eq!(
+        describe(&overlay, 0..20),
+        vec![
+            (0, 1, "-@1".to_string()),
+            (1, 2, "base".to_string()),
+            (2, 4, "alice@1".to_string()),
+            (4, 8, "bob@0".to_string()),
+            (8, 10, "base".to_string()),
+        ]
+    );
+    assert_eq!(overlay.blame(5).unwrap().metadata().unwrap().tags, vec!["review"]);
+    assert!(overlay.blame(10).is_none());
+}
+
+/// test that metadata attached to a single patch takes precedence over the
+/// metadata of the overlay
+#[test]
+fn test_patch_metadata() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123456789"[..]));
+    overlay.set_metadata(Some(PatchMetadata::new("alice")));
+    let patch = Patch::fill(2, "x", 3)
+        .unwrap()
+        .with_metadata(PatchMetadata::new("bob").with_reason("fill"));
+    overlay.add_patch(patch).unwrap();
+    overlay.add_bytes_at(6, "a").unwrap();
+
+    let bob = overlay.blame(3).unwrap();
+    assert_eq!(bob.metadata().unwrap().author, "bob");
+    assert_eq!(bob.metadata().unwrap().reason, "fill");
+    assert!(bob.metadata().unwrap().timestamp.is_some());
+    assert_eq!(overlay.blame(6).unwrap().metadata().unwrap().author, "alice");
+    assert_eq!(overlay.read_range(&(0..10)).unwrap(), b"01xxx5a789");
+}
+
+/// test that metadata survives reverting and compacting
+#[test]
+fn test_blame_after_compact() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123456789"[..]));
+    overlay.set_metadata(Some(PatchMetadata::new("alice")));
+    overlay.add_bytes_at(0, "aaaaaa").unwrap();
+    overlay.write_masked_at(6, [0xff], [0x01]).unwrap();
+    overlay.set_metadata(None);
+    overlay.add_bytes_at(2, "xx").unwrap();
+    overlay.revert(3..4).unwrap();
+    overlay.compact().unwrap();
+
+    assert_eq!(
+        describe(&overlay, 0..10),
+        vec![
+            (0, 2, "alice@0".to_string()),
+            (2, 3, "-@0".to_string()),
+            (3, 4, "base".to_string()),
+            (4, 6, "alice@0".to_string()),
+            (6, 7, "alice@0".to_string()),
+            (7, 10, "base".to_string()),
+        ]
+    );
+}
+
+/// test that explicit timestamps are kept and gaps are reported
+#[test]
+fn test_blame_timestamp_and_gap() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123"[..]));
+    overlay.set_write_policy(WritePolicy {
+        allow_gap: true,
+        ..Default::default()
+    });
+    let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    overlay.set_metadata(Some(PatchMetadata::new("carol").with_timestamp(timestamp)));
+    overlay.add_bytes_at(6, "z").unwrap();
+
+    assert_eq!(
+        describe(&overlay, 2..7),
+        vec![
+            (2, 4, "base".to_string()),
+            (4, 6, "gap".to_string()),
+            (6, 7, "carol@0".to_string()),
+        ]
+    );
+    assert_eq!(overlay.blame(6).unwrap().metadata().unwrap().timestamp, Some(timestamp));
+    assert!(overlay.blame(7).is_none());
+    assert!(overlay.blame(u64::MAX).is_none());
+}
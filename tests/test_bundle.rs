@@ -0,0 +1,132 @@
+#![cfg(feature = "bundle")]
+
+mod common;
+
+use common::overlay_error;
+use memoverlay::{MemOverlay, OverlayError, PatchBundle};
+use std::io::Cursor;
+
+/// sha256 of "........"
+const MANIFEST: &str = r#"
+name = "test"
+
+[[preconditions]]
+kind = "sha256"
+digest = "d09f0ad06d6e59b908037317d6c044f7ababd3466cb46d5b901cf6f0855baf04"
+
+[[groups]]
+name = "second"
+depends_on = ["first"]
+patches = [{ offset = 2, bytes = "58 58", expected = "41 41" }]
+
+[[groups]]
+name = "first"
+patches = [{ offset = 0, payload = "a.bin" }]
+"#;
+
+/// test loading a bundle from a directory and applying it in the order of
+/// its dependencies
+#[test]
+fn test_load_and_apply() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("manifest.toml"), MANIFEST).unwrap();
+    std::fs::write(dir.path().join("a.bin"), b"AAAA").unwrap();
+    let bundle = PatchBundle::load(dir.path()).unwrap();
+    assert_eq!(bundle.payload("a.bin"), Some(&b"AAAA"[..]));
+
+    let order: Vec<_> = bundle
+        .ordered_groups()
+        .unwrap()
+        .into_iter()
+        .map(|group| group.name.as_str())
+        .collect();
+    assert_eq!(order, vec!["first", "second"]);
+
+    let mut overlay = MemOverlay::from(Cursor::new(b"........"));
+    bundle.apply(&mut overlay).unwrap();
+    assert_eq!(overlay.read_range(&(0..8)).unwrap(), b"AAXX....");
+
+    // the hash does not match anymore
+    let err = overlay_error(bundle.apply(&mut overlay).unwrap_err());
+    assert!(matches!(err, OverlayError::PreconditionFailed(_)));
+}
+
+/// test that a failing patch rolls back all other patches
+#[test]
+fn test_atomic() {
+    let mut bundle = PatchBundle::from_toml(MANIFEST).unwrap();
+    bundle.add_payload("a.bin", "ABAB");
+
+    let mut overlay = MemOverlay::from(Cursor::new(b"........"));
+    let err = overlay_error(bundle.apply(&mut overlay).unwrap_err());
+    assert!(matches!(err, OverlayError::UnexpectedBytes { offset: 2, .. }));
+    assert_eq!(overlay.patch_bytes(), 0);
+}
+
+/// test that a failing bundle is rolled back inside of an open transaction
+#[test]
+fn test_atomic_in_transaction() {
+    let mut bundle = PatchBundle::from_toml(MANIFEST).unwrap();
+    bundle.add_payload("a.bin", "ABAB");
+
+    let mut overlay = MemOverlay::from(Cursor::new(b"........"));
+    overlay.begin().unwrap();
+    assert!(bundle.apply(&mut overlay).is_err());
+    assert_eq!(overlay.read_range(&(0..8)).unwrap(), b"........");
+
+    overlay.add_bytes_at(7, "!").unwrap();
+    overlay.commit().unwrap();
+    assert_eq!(overlay.read_range(&(0..8)).unwrap(), b".......!");
+    assert_eq!(overlay.patch_bytes(), 1);
+}
+
+/// test that invalid bundles are rejected before anything is changed
+#[test]
+fn test_invalid() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"........"));
+
+    let missing_payload = PatchBundle::from_toml(MANIFEST).unwrap();
+    let cycle = PatchBundle::from_json(
+        r#"{ "name": "cycle", "groups": [
+            { "name": "a", "depends_on": ["b"] },
+            { "name": "b", "depends_on": ["a"] }
+        ] }"#,
+    )
+    .unwrap();
+    let unknown = PatchBundle::from_json(
+        r#"{ "name": "unknown", "groups": [{ "name": "a", "depends_on": ["c"] }] }"#,
+    )
+    .unwrap();
+    let overflow = PatchBundle::from_json(
+        r#"{ "name": "overflow", "groups": [], "preconditions": [
+            { "kind": "bytes", "offset": 18446744073709551615, "expected": "41 41" }
+        ] }"#,
+    )
+    .unwrap();
+
+    for bundle in [missing_payload, cycle, unknown, overflow] {
+        let err = overlay_error(bundle.apply(&mut overlay).unwrap_err());
+        assert!(matches!(err, OverlayError::InvalidBundle(_)));
+    }
+    assert!(PatchBundle::from_json("{}").is_err());
+    assert_eq!(overlay.patch_bytes(), 0);
+}
+
+/// test that payloads outside of the bundle directory are not loaded
+#[test]
+fn test_payload_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle_dir = dir.path().join("bundle");
+    std::fs::create_dir(&bundle_dir).unwrap();
+    std::fs::write(dir.path().join("secret.bin"), b"AAAA").unwrap();
+    let outside = dir.path().join("secret.bin");
+
+    for name in ["../secret.bin", "./../secret.bin", outside.to_str().unwrap(), ""] {
+        let manifest = format!(
+            "name = \"test\"\n[[groups]]\nname = \"a\"\npatches = [{{ offset = 0, payload = {name:?} }}]\n"
+        );
+        std::fs::write(bundle_dir.join("manifest.toml"), manifest).unwrap();
+        let err = overlay_error(PatchBundle::load(&bundle_dir).unwrap_err());
+        assert!(matches!(err, OverlayError::InvalidBundle(_)), "{name}");
+    }
+}
@@ -0,0 +1,97 @@
+use memoverlay::{ChangedBlock, CopyMode, MemOverlay, OverlayError, WritePolicy};
+use std::io::Cursor;
+
+/// test which blocks are reported before and after a checkpoint
+#[test]
+fn test_checkpoint() {
+    let mut overlay = MemOverlay::from(Cursor::new(vec![0u8; 100]));
+    assert!(overlay.dirty_bitmap().is_none());
+    overlay.enable_change_tracking(10).unwrap();
+
+    overlay.add_bytes_at(5, "abc").unwrap();
+    overlay.add_bytes_at(38, "xyz").unwrap();
+    overlay.add_bytes_at(95, "!").unwrap();
+    let bitmap = overlay.dirty_bitmap().unwrap();
+    assert_eq!(bitmap.block_count(), 10);
+    assert_eq!(bitmap.dirty_blocks().collect::<Vec<_>>(), vec![0, 3, 4, 9]);
+    assert_eq!(bitmap.as_bytes(), [0b0001_1001, 0b0000_0010]);
+
+    overlay.checkpoint();
+    assert_eq!(overlay.dirty_bitmap().unwrap().dirty_count(), 0);
+
+    overlay.revert(0..10).unwrap();
+    overlay.revert(50..60).unwrap();
+    let bitmap = overlay.dirty_bitmap().unwrap();
+    assert_eq!(bitmap.dirty_blocks().collect::<Vec<_>>(), vec![0]);
+}
+
+/// test that the exported blocks contain the current bytes
+#[test]
+fn test_export() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!".to_vec()));
+    overlay.set_write_policy(WritePolicy {
+        allow_gap: true,
+        ..Default::default()
+    });
+    overlay.enable_change_tracking(4).unwrap();
+    overlay.add_bytes_at(7, "peter").unwrap();
+    overlay.add_bytes_at(17, "?").unwrap();
+
+    // the gap between the old end and the new patch is new as well
+    assert_eq!(
+        overlay.export_changed_blocks().unwrap(),
+        vec![
+            ChangedBlock {
+                offset: 4,
+                bytes: b"o, p".to_vec()
+            },
+            ChangedBlock {
+                offset: 8,
+                bytes: b"eter".to_vec()
+            },
+            ChangedBlock {
+                offset: 12,
+                bytes: b"!\0\0\0".to_vec()
+            },
+            ChangedBlock {
+                offset: 16,
+                bytes: b"\0?".to_vec()
+            },
+        ]
+    );
+}
+
+/// test that live copies of changed bytes are reported as well
+#[test]
+fn test_live_copy() {
+    let mut overlay = MemOverlay::from(Cursor::new(vec![0u8; 64]));
+    overlay.copy_within(0..8, 48, CopyMode::Live).unwrap();
+    overlay.enable_change_tracking(16).unwrap();
+
+    overlay.add_bytes_at(2, "abc").unwrap();
+    let bitmap = overlay.dirty_bitmap().unwrap();
+    assert_eq!(bitmap.dirty_blocks().collect::<Vec<_>>(), vec![0, 3]);
+
+    assert!(matches!(
+        overlay.enable_change_tracking(0),
+        Err(OverlayError::InvalidBlockSize(0))
+    ));
+}
+
+/// test that the blocks which are restored by an abort are reported, even if
+/// a checkpoint has been set inside of the transaction
+#[test]
+fn test_abort() {
+    let mut overlay = MemOverlay::from(Cursor::new(vec![0u8; 100]));
+    overlay.add_bytes_at(25, "abc").unwrap();
+    overlay.enable_change_tracking(10).unwrap();
+
+    overlay.begin().unwrap();
+    overlay.revert(0..100).unwrap();
+    overlay.checkpoint();
+    overlay.abort().unwrap();
+
+    let bitmap = overlay.dirty_bitmap().unwrap();
+    assert!(bitmap.dirty_count() > 0);
+    assert!(bitmap.is_dirty(2));
+}
@@ -0,0 +1,48 @@
+use memoverlay::{AddLe, ByteSwap, MemOverlay, SharedSource, Xor};
+use std::io::{Cursor, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+
+/// test that computed patches follow changes of the bytes beneath them
+#[test]
+fn test_counter_follows_below() {
+    let donor = Arc::new(Mutex::new(Cursor::new(41u32.to_le_bytes().to_vec())));
+    let source: SharedSource = donor.clone();
+
+    let mut overlay = MemOverlay::from(Cursor::new([0u8; 8]));
+    overlay.add_external_at(4, source, 0..4).unwrap();
+    overlay.transform_at(4, 4, AddLe(1)).unwrap();
+    assert_eq!(overlay.read_u32_le_at(4).unwrap(), 42);
+
+    let mut donor = donor.lock().unwrap();
+    donor.seek(SeekFrom::Start(0)).unwrap();
+    donor.write_all(&0xffffu32.to_le_bytes()).unwrap();
+    drop(donor);
+    assert_eq!(overlay.read_u32_le_at(4).unwrap(), 0x10000);
+}
+
+/// test xor, byte swapping and closures
+#[test]
+fn test_transforms() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"abcdefgh"));
+    overlay.transform_at(0, 2, Xor::new([0x20])).unwrap();
+    overlay.transform_at(4, 4, ByteSwap).unwrap();
+    overlay.transform_at(2, 1, |bytes: &mut [u8]| bytes.fill(b'-')).unwrap();
+    assert_eq!(overlay.read_range(&(0..8)).unwrap(), b"AB-dhgfe");
+
+    // a partial read sees the part of the whole transformed patch
+    assert_eq!(overlay.read_range(&(5..7)).unwrap(), b"gf");
+}
+
+/// test that computed patches keep their content when they are sliced or
+/// compacted
+#[test]
+fn test_slice_and_compact() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"abcdefgh"));
+    overlay.transform_at(0, 8, ByteSwap).unwrap();
+    overlay.revert(2..4).unwrap();
+    assert_eq!(overlay.read_range(&(0..8)).unwrap(), b"hgcddcba");
+
+    overlay.add_bytes_at(0, "x").unwrap();
+    overlay.compact().unwrap();
+    assert_eq!(overlay.read_range(&(0..8)).unwrap(), b"xgcddcba");
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::overlay_error;
+use memoverlay::{CopyMode, MemOverlay, OverlayError, XorEncoding};
+use std::io::{Cursor, Read};
+
+/// test that a snapshot copy keeps the bytes it had when it was created
+#[test]
+fn test_snapshot_copy() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"0123456789abcdef"));
+    overlay.add_bytes_at(2, "xy").unwrap();
+    overlay.copy_within(0..4, 8, CopyMode::Snapshot).unwrap();
+    overlay.add_bytes_at(0, "AB").unwrap();
+    overlay.revert(2..4).unwrap();
+
+    let mut data = String::new();
+    overlay.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "AB23456701xycdef");
+    assert_eq!(overlay.patch_bytes(), 6);
+}
+
+/// test that a live copy always shows the current bytes of its source
+#[test]
+fn test_live_copy() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"0123456789abcdef"));
+    overlay.copy_within(0..4, 8, CopyMode::Live).unwrap();
+    overlay.add_bytes_at(1, "xy").unwrap();
+    assert_eq!(overlay.read_range(&(6..14)).unwrap(), b"670xy3cd");
+
+    // copies of copies are resolved as well
+    overlay.copy_within(9..11, 14, CopyMode::Live).unwrap();
+    overlay.compact().unwrap();
+    overlay.add_bytes_at(2, "z").unwrap();
+    assert_eq!(overlay.read_range(&(8..16)).unwrap(), b"0xz3cdxz");
+}
+
+/// test that a snapshot copy keeps the base encoding it had when it was created
+#[test]
+fn test_snapshot_encoding() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"abcdEFGH"));
+    overlay.copy_within(0..4, 4, CopyMode::Snapshot).unwrap();
+    overlay.set_base_encoding(XorEncoding::new([0x20])).unwrap();
+    assert_eq!(overlay.read_range(&(0..8)).unwrap(), b"ABCDabcd");
+}
+
+/// test that invalid copies are rejected
+#[test]
+fn test_invalid_copy() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"0123456789"));
+
+    let err = overlay.copy_within(0..4, 2, CopyMode::Live).unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(err, OverlayError::OverlappingCopy { begin: 0, end: 4, offset: 2 }));
+
+    let err = overlay.copy_within(8..12, 0, CopyMode::Snapshot).unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(err, OverlayError::CopySourceOutOfRange { data_len: 10, .. }));
+
+    // overlapping snapshots behave like memmove
+    overlay.copy_within(0..4, 2, CopyMode::Snapshot).unwrap();
+    assert_eq!(overlay.read_range(&(0..10)).unwrap(), b"0101236789");
+
+    let err = overlay.copy_within(0..4, u64::MAX - 1, CopyMode::Snapshot).unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(err, OverlayError::OffsetOverflow { .. }));
+}
+
+/// test that cyclic live copies are rejected when they are created
+#[test]
+fn test_cyclic_copy() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"0123456789"));
+    overlay.copy_within(0..2, 4, CopyMode::Live).unwrap();
+    overlay.copy_within(4..6, 8, CopyMode::Live).unwrap();
+
+    for (source, offset) in [(4..6, 0), (8..10, 1), (8..9, 0)] {
+        let err = overlay.copy_within(source, offset, CopyMode::Live).unwrap_err();
+        let err = overlay_error(err);
+        assert!(matches!(err, OverlayError::CyclicCopy { .. }));
+    }
+    assert_eq!(overlay.read_range(&(0..10)).unwrap(), b"0123016701");
+
+    // a copy of a part which is not shown by the source is fine
+    overlay.copy_within(8..10, 2, CopyMode::Live).unwrap();
+    assert_eq!(overlay.read_range(&(0..10)).unwrap(), b"0101016701");
+}
@@ -0,0 +1,47 @@
+use memoverlay::{MemOverlay, SharedSource};
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// test transplanting a region of another file
+#[test]
+fn test_external_file() {
+    let mut donor = tempfile::tempfile().unwrap();
+    donor.write_all(b"0123456789abcdef").unwrap();
+    let source: SharedSource = Arc::new(Mutex::new(donor));
+
+    let mut overlay = MemOverlay::from(Cursor::new(b"................"));
+    overlay.add_external_at(2, Arc::clone(&source), 10..14).unwrap();
+    overlay.add_external_at(8, source, 0..4).unwrap();
+    overlay.revert(3..4).unwrap();
+
+    let mut data = String::new();
+    overlay.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "..a.cd..0123....");
+}
+
+/// test that a source which is too short causes an error while reading
+#[test]
+fn test_short_source() {
+    let source: SharedSource = Arc::new(Mutex::new(Cursor::new(b"abc")));
+    let mut overlay = MemOverlay::from(Cursor::new(b"........"));
+    overlay.add_external_at(0, source, 2..6).unwrap();
+
+    let mut buf = [0; 8];
+    assert!(overlay.read_exact_at(0, &mut buf).is_err());
+}
+
+/// test that external patches are read only when needed
+#[test]
+fn test_lazy_read() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let source: SharedSource = Arc::new(Mutex::new(File::open(file.path()).unwrap()));
+
+    let mut overlay = MemOverlay::from(Cursor::new(b"........"));
+    overlay.add_external_at(4, source, 0..4).unwrap();
+
+    std::fs::write(file.path(), b"late").unwrap();
+    let mut data = String::new();
+    overlay.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "....late");
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::overlay_error;
+use memoverlay::{AddLe, MemOverlay, OverlayError, ProtectionMode, SharedSource, WritePolicy};
+use std::sync::{Arc, Mutex};
+use std::io::{Cursor, Read};
+
+/// test a huge fill patch, which would not fit into memory as a byte patch
+#[test]
+fn test_huge_fill() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"0123456789"));
+    overlay.fill_at(4, "ab", 1 << 40).unwrap();
+    assert_eq!(overlay.data_len(), (1 << 40) + 4);
+
+    let mut buf = [0; 4];
+    overlay.read_exact_at(1 << 39, &mut buf).unwrap();
+    assert_eq!(&buf, b"abab");
+    let mut buf = [0; 3];
+    overlay.read_exact_at((1 << 40) + 1, &mut buf).unwrap();
+    assert_eq!(&buf, b"bab");
+}
+
+/// test that split fill patches keep the phase of their pattern
+#[test]
+fn test_split_fill() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"..........."));
+    overlay.set_write_policy(WritePolicy {
+        protection_mode: ProtectionMode::Clip,
+        ..Default::default()
+    });
+    overlay.protect(3..4);
+    overlay.fill_at(1, "abc", 9).unwrap();
+    overlay.revert(6..7).unwrap();
+    overlay.add_bytes_at(8, "X").unwrap();
+    overlay.compact().unwrap();
+
+    let mut data = String::new();
+    overlay.read_to_string(&mut data).unwrap();
+    assert_eq!(data, ".ab.ab.aXc.");
+}
+
+/// test that patches which would end beyond the largest possible offset are
+/// rejected
+#[test]
+fn test_offset_overflow() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"0123456789"));
+    let source: SharedSource = Arc::new(Mutex::new(Cursor::new([0u8; 16])));
+    let offset = u64::MAX - 1;
+    let results = [
+        overlay.fill_at(offset, "x", 10),
+        overlay.add_bytes_at(offset, "xyz").map(|_| ()),
+        overlay.write_masked_at(offset, [1, 2], [3, 4]),
+        overlay.transform_at(offset, 4, AddLe(1)),
+        overlay.add_external_at(offset, source, 0..16),
+    ];
+    for result in results {
+        let err = overlay_error(result.unwrap_err());
+        assert!(matches!(err, OverlayError::OffsetOverflow { offset: o, .. } if o == offset));
+    }
+    assert_eq!(overlay.patch_bytes(), 0);
+}
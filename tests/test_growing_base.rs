@@ -0,0 +1,60 @@
+use memoverlay::MemOverlay;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+fn growing_file(content: &[u8]) -> (tempfile::NamedTempFile, File) {
+    let mut writer = tempfile::NamedTempFile::new().unwrap();
+    writer.write_all(content).unwrap();
+    writer.flush().unwrap();
+    let reader = File::open(writer.path()).unwrap();
+    (writer, reader)
+}
+
+fn append(writer: &tempfile::NamedTempFile, content: &[u8]) {
+    let mut file = OpenOptions::new().append(true).open(writer.path()).unwrap();
+    file.write_all(content).unwrap();
+}
+
+/// test that new base data becomes visible after an explicit refresh
+#[test]
+fn test_refresh_base_len() {
+    let (writer, reader) = growing_file(b"0123456789");
+    let mut overlay = MemOverlay::from(reader);
+    assert_eq!(overlay.base_len(), Some(10));
+
+    append(&writer, b"abcdef");
+    assert!(overlay.seek(SeekFrom::Start(12)).is_err());
+
+    assert_eq!(overlay.refresh_base_len().unwrap(), 16);
+    overlay.seek(SeekFrom::Start(12)).unwrap();
+
+    let mut dst = String::new();
+    overlay.read_to_string(&mut dst).unwrap();
+    assert_eq!(dst, "cdef");
+}
+
+/// test that patches behind the previous end of the base keep their
+/// precedence over new base data
+#[test]
+fn test_auto_refresh_keeps_patches() {
+    let (writer, reader) = growing_file(b"0123456789");
+    let mut overlay = MemOverlay::from(reader);
+    overlay.set_auto_refresh_base_len(true);
+    overlay.add_bytes_at(8, b"XYZ").unwrap();
+
+    let mut dst = String::new();
+    overlay.read_to_string(&mut dst).unwrap();
+    assert_eq!(dst, "01234567XYZ");
+
+    append(&writer, b"abcdef");
+
+    let mut dst = String::new();
+    overlay.read_to_string(&mut dst).unwrap();
+    assert_eq!(dst, "bcdef");
+    assert_eq!(overlay.base_len(), Some(16));
+
+    overlay.seek(SeekFrom::End(-2)).unwrap();
+    let mut dst = String::new();
+    overlay.read_to_string(&mut dst).unwrap();
+    assert_eq!(dst, "def");
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::overlay_error;
+use memoverlay::{MemOverlay, OverlayError, SharedSource};
+use std::io::{Cursor, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+
+/// test that masked bits follow changes of the bytes beneath them
+#[test]
+fn test_masked_follows_below() {
+    let donor = Arc::new(Mutex::new(Cursor::new(vec![0x00u8; 4])));
+    let source: SharedSource = donor.clone();
+
+    let mut overlay = MemOverlay::from(Cursor::new([0xffu8; 8]));
+    overlay.add_external_at(2, source, 0..4).unwrap();
+    overlay.write_masked_at(1, [0x01, 0x01, 0x80], [0x0f, 0x0f, 0xf0]).unwrap();
+    assert_eq!(overlay.read_range(&(0..5)).unwrap(), [0xff, 0xf1, 0x01, 0x80, 0x00]);
+
+    // change the bytes beneath the masked patch
+    let mut donor = donor.lock().unwrap();
+    donor.seek(SeekFrom::Start(0)).unwrap();
+    donor.write_all(&[0xaa, 0xaa]).unwrap();
+    drop(donor);
+    assert_eq!(overlay.read_range(&(0..5)).unwrap(), [0xff, 0xf1, 0xa1, 0x8a, 0x00]);
+}
+
+/// test that masked patches can be stacked and compacted
+#[test]
+fn test_stacked_masks() {
+    let mut overlay = MemOverlay::from(Cursor::new([0x00u8; 4]));
+    overlay.write_masked_at(1, [0x01], [0x01]).unwrap();
+    overlay.write_masked_at(1, [0x80], [0x80]).unwrap();
+    overlay.write_masked_at(0, [0xff, 0x00], [0x0f, 0x01]).unwrap();
+    assert_eq!(overlay.read_range(&(0..4)).unwrap(), [0x0f, 0x80, 0x00, 0x00]);
+
+    overlay.compact().unwrap();
+    assert_eq!(overlay.read_range(&(0..4)).unwrap(), [0x0f, 0x80, 0x00, 0x00]);
+    overlay.revert(0..1).unwrap();
+    assert_eq!(overlay.read_range(&(0..4)).unwrap(), [0x00, 0x80, 0x00, 0x00]);
+}
+
+/// test that value and mask must have the same length
+#[test]
+fn test_mask_length() {
+    let mut overlay = MemOverlay::from(Cursor::new([0x00u8; 4]));
+    let err = overlay.write_masked_at(0, [1, 2], [1]).unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(err, OverlayError::MaskLengthMismatch { value_len: 2, mask_len: 1 }));
+}
+
+/// test that large masked patches are compacted into several patches
+#[test]
+fn test_compact_large_mask() {
+    let len = 3 << 20;
+    let mut overlay = MemOverlay::from(Cursor::new(vec![0x10u8; len + 2]));
+    overlay.write_masked_at(1, vec![0x01; len], vec![0x0f; len]).unwrap();
+    overlay.compact().unwrap();
+
+    let entries = overlay.blame_range(0..len as u64 + 2);
+    assert!(entries.iter().all(|entry| entry.range.end - entry.range.start <= 1 << 20));
+    let bytes = overlay.read_range(&(0..len as u64 + 2)).unwrap();
+    assert_eq!(bytes[0], 0x10);
+    assert!(bytes[1..=len].iter().all(|byte| *byte == 0x11));
+    assert_eq!(bytes[len + 1], 0x10);
+}
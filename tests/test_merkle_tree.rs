@@ -0,0 +1,164 @@
+#![cfg(feature = "hash")]
+
+use memoverlay::{CopyMode, MemOverlay, OverlayError, SharedSource, WritePolicy, XorEncoding};
+use std::io::{Cursor, Read, Result, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// a base which counts the bytes which have been read from it
+struct CountingBase {
+    inner: Cursor<Vec<u8>>,
+    read: Arc<AtomicU64>,
+}
+
+impl Read for CountingBase {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.read.fetch_add(count as u64, Ordering::Relaxed);
+        Ok(count)
+    }
+}
+
+impl Seek for CountingBase {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+fn fresh_root<R: Read + Seek>(overlay: &mut MemOverlay<R>, block_size: u64) -> [u8; 32] {
+    let bytes = overlay.read_range(&(0..overlay.data_len())).unwrap();
+    let mut fresh = MemOverlay::from(Cursor::new(bytes));
+    fresh.enable_merkle_tree(block_size).unwrap();
+    fresh.merkle_tree().unwrap().unwrap().root()
+}
+
+/// test that only the blocks which have been changed are read again
+#[test]
+fn test_incremental_update() {
+    let read = Arc::new(AtomicU64::new(0));
+    let base = CountingBase {
+        inner: Cursor::new((0..64 * 1024).map(|i| i as u8).collect()),
+        read: Arc::clone(&read),
+    };
+    let mut overlay = MemOverlay::from(base);
+    overlay.enable_merkle_tree(4096).unwrap();
+    overlay.merkle_tree().unwrap();
+    assert_eq!(read.swap(0, Ordering::Relaxed), 64 * 1024);
+
+    overlay.add_bytes_at(5000, [0xff]).unwrap();
+    overlay.add_bytes_at(5001, [0xfe]).unwrap();
+    let root = overlay.merkle_tree().unwrap().unwrap().root();
+    assert_eq!(read.swap(0, Ordering::Relaxed), 4094);
+    assert_eq!(root, fresh_root(&mut overlay, 4096));
+
+    // nothing has changed since the last update
+    read.store(0, Ordering::Relaxed);
+    overlay.merkle_tree().unwrap();
+    assert_eq!(read.load(Ordering::Relaxed), 0);
+
+    overlay.revert(5000..5001).unwrap();
+    let root = overlay.merkle_tree().unwrap().unwrap().root();
+    assert_eq!(root, fresh_root(&mut overlay, 4096));
+}
+
+/// test that the tree follows changes of the length of the data
+#[test]
+fn test_growth_and_shrinking() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!".to_vec()));
+    overlay.set_write_policy(WritePolicy {
+        allow_gap: true,
+        ..Default::default()
+    });
+    overlay.enable_merkle_tree(4).unwrap();
+    assert_eq!(overlay.merkle_tree().unwrap().unwrap().block_count(), 4);
+
+    overlay.add_bytes_at(30, "!").unwrap();
+    let tree = overlay.merkle_tree().unwrap().unwrap();
+    assert_eq!(tree.block_count(), 8);
+    assert_eq!(tree.block_range(7), 28..31);
+    let root = tree.root();
+    assert_eq!(root, fresh_root(&mut overlay, 4));
+
+    overlay.revert(13..31).unwrap();
+    let root = overlay.merkle_tree().unwrap().unwrap().root();
+    assert_eq!(root, fresh_root(&mut overlay, 4));
+
+    let mut original = MemOverlay::from(Cursor::new(b"hello, world!".to_vec()));
+    original.enable_merkle_tree(4).unwrap();
+    assert_eq!(root, original.merkle_tree().unwrap().unwrap().root());
+}
+
+/// test that writing the source of a live copy changes the hash of the copy
+#[test]
+fn test_live_copy() {
+    let mut overlay = MemOverlay::from(Cursor::new(vec![0u8; 64]));
+    overlay.copy_within(0..8, 48, CopyMode::Live).unwrap();
+    overlay.enable_merkle_tree(16).unwrap();
+    overlay.merkle_tree().unwrap();
+
+    overlay.add_bytes_at(2, "abc").unwrap();
+    let root = overlay.merkle_tree().unwrap().unwrap().root();
+    assert_eq!(root, fresh_root(&mut overlay, 16));
+}
+
+#[test]
+fn test_invalid_block_size() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    assert!(matches!(
+        overlay.enable_merkle_tree(0),
+        Err(OverlayError::InvalidBlockSize(0))
+    ));
+    assert!(overlay.merkle_tree().unwrap().is_none());
+}
+
+/// test that the tree matches the data after a transaction has been aborted
+#[test]
+fn test_abort() {
+    let mut overlay = MemOverlay::from(Cursor::new(vec![0u8; 4096]));
+    overlay.enable_merkle_tree(256).unwrap();
+    let root = overlay.merkle_tree().unwrap().unwrap().root();
+
+    overlay.begin().unwrap();
+    overlay.add_bytes_at(1000, "peter").unwrap();
+    assert_ne!(overlay.merkle_tree().unwrap().unwrap().root(), root);
+    overlay.abort().unwrap();
+
+    assert_eq!(overlay.merkle_tree().unwrap().unwrap().root(), root);
+    assert_eq!(root, fresh_root(&mut overlay, 256));
+}
+
+/// test that the tree follows changes of the base encoding, and changes
+/// which are reported with `invalidate`
+#[test]
+fn test_invalidate() {
+    let mut overlay = MemOverlay::from(Cursor::new(vec![0u8; 4096]));
+    let source = Arc::new(Mutex::new(Cursor::new(vec![1u8; 16])));
+    let shared: SharedSource = source.clone();
+    overlay.add_external_at(2000, shared, 0..16).unwrap();
+    overlay.enable_merkle_tree(256).unwrap();
+    overlay.merkle_tree().unwrap();
+
+    overlay.set_base_encoding(XorEncoding::new([0x20])).unwrap();
+    let root = overlay.merkle_tree().unwrap().unwrap().root();
+    assert_eq!(root, fresh_root(&mut overlay, 256));
+
+    source.lock().unwrap().get_mut()[5] = 2;
+    overlay.invalidate(2005..2006);
+    assert_ne!(overlay.merkle_tree().unwrap().unwrap().root(), root);
+    assert_eq!(overlay.merkle_tree().unwrap().unwrap().root(), fresh_root(&mut overlay, 256));
+}
+
+/// test that the new last block is hashed again when the base shrinks
+#[test]
+fn test_shrinking_base() {
+    let mut file = tempfile::tempfile().unwrap();
+    file.write_all(&[7; 4096]).unwrap();
+    let mut overlay = MemOverlay::from(file.try_clone().unwrap());
+    overlay.enable_merkle_tree(1024).unwrap();
+    overlay.merkle_tree().unwrap();
+
+    file.set_len(3000).unwrap();
+    assert_eq!(overlay.refresh_base_len().unwrap(), 3000);
+    let root = overlay.merkle_tree().unwrap().unwrap().root();
+    assert_eq!(root, fresh_root(&mut overlay, 1024));
+}
@@ -0,0 +1,77 @@
+use memoverlay::{CopyMode, MemOverlay, WritePolicy};
+use std::io::Cursor;
+
+fn modified_ranges(
+    overlay: &mut MemOverlay<Cursor<&[u8]>>,
+) -> Vec<(std::ops::Range<u64>, Vec<u8>)> {
+    overlay.modified_ranges().collect::<Result<_, _>>().unwrap()
+}
+
+/// test that hidden parts of older patches are left out
+#[test]
+fn test_overlapping_layers() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"hello, world!"[..]));
+    overlay.add_bytes_at(3, "XXXX").unwrap();
+    overlay.add_bytes_at(5, "YYYY").unwrap();
+    overlay.add_bytes_at(8, "ZZ").unwrap();
+    overlay.add_bytes_at(0, "a").unwrap();
+
+    assert_eq!(
+        modified_ranges(&mut overlay),
+        vec![
+            (0..1, b"a".to_vec()),
+            (3..5, b"XX".to_vec()),
+            (5..8, b"YYY".to_vec()),
+            (8..10, b"ZZ".to_vec()),
+        ]
+    );
+    assert_eq!(overlay.modified_len(), 8);
+
+    overlay.revert(4..9).unwrap();
+    assert_eq!(
+        modified_ranges(&mut overlay),
+        vec![
+            (0..1, b"a".to_vec()),
+            (3..4, b"X".to_vec()),
+            (9..10, b"Z".to_vec())
+        ]
+    );
+    assert_eq!(overlay.modified_len(), 3);
+}
+
+/// test the visible bytes of patches which do not store their bytes
+#[test]
+fn test_special_patches() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"hello, world!"[..]));
+    overlay.set_write_policy(WritePolicy {
+        allow_gap: true,
+        ..Default::default()
+    });
+    overlay.copy_within(0..5, 7, CopyMode::Live).unwrap();
+    overlay.fill_at(15, "ab", 3).unwrap();
+    overlay.add_bytes_at(0, "J").unwrap();
+
+    assert_eq!(
+        modified_ranges(&mut overlay),
+        vec![
+            (0..1, b"J".to_vec()),
+            (7..12, b"Jello".to_vec()),
+            (15..18, b"aba".to_vec()),
+        ]
+    );
+}
+
+/// test which ranges count as modified
+#[test]
+fn test_is_modified() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"hello, world!"[..]));
+    assert!(!overlay.is_modified(0..13));
+    assert_eq!(overlay.modified_len(), 0);
+
+    overlay.add_bytes_at(7, "world").unwrap();
+    assert!(overlay.is_modified(11..20));
+    assert!(overlay.is_modified(0..8));
+    assert!(!overlay.is_modified(0..7));
+    assert!(!overlay.is_modified(12..13));
+    assert!(!overlay.is_modified(8..8));
+}
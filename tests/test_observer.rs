@@ -0,0 +1,113 @@
+use memoverlay::{ChangeBytes, ChangeEvent, ChangeKind, CopyMode, MemOverlay, MAX_EVENT_BYTES};
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Mutex};
+
+type Events = Arc<Mutex<Vec<ChangeEvent>>>;
+
+fn observed_overlay(data: &'static [u8]) -> (MemOverlay<Cursor<&'static [u8]>>, Events) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut overlay = MemOverlay::from(Cursor::new(data));
+    let recorder = Arc::clone(&events);
+    overlay.add_observer(move |event| recorder.lock().unwrap().push(event.clone()));
+    (overlay, events)
+}
+
+/// test that writes, reverts and compactions are reported
+#[test]
+fn test_events() {
+    let (mut overlay, events) = observed_overlay(b"hello, world!");
+    overlay.add_bytes_at(3, "XXXX").unwrap();
+    overlay.add_bytes_at(5, "YYYY").unwrap();
+    overlay.revert(0..4).unwrap();
+    overlay.compact().unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 4);
+
+    assert_eq!(events[1].kind, ChangeKind::Write);
+    assert_eq!(events[1].range, 5..9);
+    assert_eq!(events[1].old_bytes, b"XXwo");
+    assert_eq!(events[1].new_bytes, b"YYYY");
+
+    assert_eq!(events[2].kind, ChangeKind::Revert);
+    assert_eq!(events[2].old_bytes, b"helX");
+    assert_eq!(events[2].new_bytes, b"hell");
+
+    assert_eq!(events[3].kind, ChangeKind::Compaction);
+    assert_eq!(events[3].range, 4..9);
+    assert_eq!(events[3].old_bytes, events[3].new_bytes);
+}
+
+/// test that changes which are visible through live copies are reported
+#[test]
+fn test_live_copy_events() {
+    let (mut overlay, events) = observed_overlay(b"0123456789abcdef");
+    overlay.copy_within(0..4, 8, CopyMode::Live).unwrap();
+    overlay.copy_within(8..10, 14, CopyMode::Live).unwrap();
+    events.lock().unwrap().clear();
+
+    overlay.add_bytes_at(1, "xy").unwrap();
+    let events = events.lock().unwrap();
+    let ranges: Vec<_> = events.iter().map(|event| event.range.clone()).collect();
+    assert_eq!(ranges, vec![1..3, 9..11, 15..16]);
+    assert!(events.iter().all(|event| event.kind == ChangeKind::Write));
+
+    assert_eq!(events[1].old_bytes, b"12");
+    assert_eq!(events[1].new_bytes, b"xy");
+    assert_eq!(events[2].old_bytes, b"1");
+    assert_eq!(events[2].new_bytes, b"x");
+}
+
+/// test that compaction does not change the visible data
+#[test]
+fn test_compact() {
+    let (mut overlay, events) = observed_overlay(b"hello, world!");
+    overlay.add_bytes_at(3, "XXXX").unwrap();
+    overlay.add_bytes_at(5, "YYYY").unwrap();
+    overlay.add_bytes_at(8, "ZZ").unwrap();
+    overlay.compact().unwrap();
+    assert_eq!(overlay.patch_bytes(), 7);
+
+    let mut message = String::new();
+    overlay.read_to_string(&mut message).unwrap();
+    assert_eq!(message, "helXXYYYZZld!");
+    assert_eq!(events.lock().unwrap().len(), 4);
+}
+
+/// test that removed observers are not called anymore
+#[test]
+fn test_remove_observer() {
+    let events = Arc::new(Mutex::new(0));
+    let mut overlay = MemOverlay::from(Cursor::new(b"hello, world!"));
+    let counter = Arc::clone(&events);
+    let id = overlay.add_observer(move |_| *counter.lock().unwrap() += 1);
+
+    overlay.add_bytes_at(0, "H").unwrap();
+    assert!(overlay.remove_observer(id));
+    overlay.add_bytes_at(7, "W").unwrap();
+    assert!(!overlay.remove_observer(id));
+    assert_eq!(*events.lock().unwrap(), 1);
+}
+
+/// test that the bytes of large ranges are not copied into the events
+#[test]
+fn test_large_fill() {
+    let (mut overlay, events) = observed_overlay(b"");
+    overlay.fill_at(0, "ab", MAX_EVENT_BYTES + 10).unwrap();
+    overlay.fill_at(3, "cd", MAX_EVENT_BYTES + 1).unwrap();
+    overlay.revert(0..MAX_EVENT_BYTES + 10).unwrap();
+
+    let events = events.lock().unwrap();
+    let bytes: Vec<_> = events.iter().map(|event| (&event.old_bytes, &event.new_bytes)).collect();
+    let fill = |pattern: &[u8]| ChangeBytes::Fill {
+        pattern: pattern.to_vec(),
+    };
+    assert_eq!(
+        bytes,
+        vec![
+            (&ChangeBytes::Omitted, &fill(b"ab")),
+            (&fill(b"ba"), &fill(b"cd")),
+            (&ChangeBytes::Omitted, &ChangeBytes::Omitted),
+        ]
+    );
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::{overlay_error, read_all};
+use memoverlay::{MemOverlay, OverlayError, ProtectionMode, WritePolicy};
+use std::io::Cursor;
+
+/// test that writes to protected regions fail without changing any data
+#[test]
+fn test_reject_protected() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123456789"[..]));
+    overlay.protect(2..4);
+
+    let err = overlay.add_bytes_at(0, "abc").unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(
+        err,
+        OverlayError::ProtectedRegion { offset: 0, len: 3, begin: 2, end: 4 }
+    ));
+    assert_eq!(read_all(&mut overlay), b"0123456789");
+}
+
+/// test that writes are clipped at protected regions
+#[test]
+fn test_clip_protected() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123456789"[..]));
+    overlay.set_write_policy(WritePolicy {
+        protection_mode: ProtectionMode::Clip,
+        ..Default::default()
+    });
+    overlay.protect(2..4);
+    overlay.protect(6..7);
+
+    assert_eq!(overlay.add_bytes_at(1, "abcdefgh").unwrap(), 8);
+    assert_eq!(read_all(&mut overlay), b"0a23de6gh9");
+    assert_eq!(overlay.patch_bytes(), 5);
+
+    // nothing at all would be written
+    let err = overlay.add_bytes_at(2, "XX").unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(
+        err,
+        OverlayError::ProtectedRegion { offset: 2, len: 2, begin: 2, end: 4 }
+    ));
+    assert_eq!(read_all(&mut overlay), b"0a23de6gh9");
+}
+
+/// test that reverts follow the protection mode
+#[test]
+fn test_revert_protected() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123456789"[..]));
+    overlay.add_bytes_at(0, "abcdefgh").unwrap();
+    overlay.protect(2..4);
+
+    let err = overlay.revert(0..6).unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(
+        err,
+        OverlayError::ProtectedRegion { offset: 0, len: 6, begin: 2, end: 4 }
+    ));
+    assert_eq!(read_all(&mut overlay), b"abcdefgh89");
+
+    // reverting unmodified protected bytes changes nothing
+    overlay.revert(6..10).unwrap();
+    overlay.protect(8..10);
+    overlay.revert(4..10).unwrap();
+    assert_eq!(read_all(&mut overlay), b"abcd456789");
+
+    overlay.set_write_policy(WritePolicy {
+        protection_mode: ProtectionMode::Clip,
+        ..Default::default()
+    });
+    overlay.revert(0..10).unwrap();
+    assert_eq!(read_all(&mut overlay), b"01cd456789");
+}
+
+/// test querying and removing protections
+#[test]
+fn test_query_protected() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123456789"[..]));
+    overlay.protect(0..2);
+    overlay.protect(2..4);
+    overlay.protect(8..10);
+    assert_eq!(overlay.protected_ranges().collect::<Vec<_>>(), vec![0..4, 8..10]);
+
+    overlay.unprotect(1..3);
+    assert_eq!(
+        overlay.protected_ranges().collect::<Vec<_>>(),
+        vec![0..1, 3..4, 8..10]
+    );
+    assert!(!overlay.is_protected(2));
+    overlay.add_bytes_at(1, "xy").unwrap();
+    assert_eq!(read_all(&mut overlay), b"0xy3456789");
+}
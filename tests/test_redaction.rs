@@ -0,0 +1,62 @@
+use memoverlay::{MemOverlay, RedactionRule, Redactor, SearchPattern};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+/// test that planning a redaction does not change any data
+#[test]
+fn test_plan() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"key=0123456789"));
+    let mut redactor = Redactor::new([0]).unwrap();
+    redactor.add_rule(RedactionRule::range("key", 4..100));
+    redactor.add_rule(RedactionRule::pattern("digits", SearchPattern::bytes("345").unwrap()));
+
+    let report = redactor.plan(&mut overlay).unwrap();
+    assert_eq!(report.entries.len(), 2);
+    assert_eq!(report.entries[0].len, 10);
+    assert_eq!(report.entries[1].rule, "digits");
+    assert_eq!(report.redacted_bytes(), 13);
+    assert_eq!(overlay.patch_bytes(), 0);
+}
+
+/// test reviewing a redaction inside of a transaction
+#[test]
+fn test_review() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"name: bob"));
+    let mut redactor = Redactor::new("xy").unwrap();
+    redactor.add_rule(RedactionRule::range("name", 6..9));
+
+    overlay.begin().unwrap();
+    redactor.apply(&mut overlay).unwrap();
+    let mut data = String::new();
+    overlay.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "name: xyx");
+
+    overlay.abort().unwrap();
+    let mut data = String::new();
+    overlay.seek(SeekFrom::Start(0)).unwrap();
+    overlay.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "name: bob");
+}
+
+/// test the builtin rules, and that the report does not contain the
+/// redacted data
+#[cfg(feature = "regex")]
+#[test]
+fn test_builtin_rules() {
+    let mut overlay = MemOverlay::from(Cursor::new(
+        b"mail john.doe@example.com, card 4111 1111 1111 1111, id 1234",
+    ));
+    let mut redactor = Redactor::new("#").unwrap();
+    redactor.add_rule(RedactionRule::email_addresses());
+    redactor.add_rule(RedactionRule::card_numbers());
+
+    let report = redactor.apply(&mut overlay).unwrap();
+    assert_eq!(report.entries.len(), 2);
+    assert!(!report.to_string().contains("john"));
+
+    let mut data = String::new();
+    overlay.read_to_string(&mut data).unwrap();
+    assert_eq!(
+        data,
+        "mail ####################, card ###################, id 1234"
+    );
+}
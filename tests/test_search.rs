@@ -0,0 +1,95 @@
+mod common;
+
+use common::overlay_error;
+use memoverlay::{MemOverlay, OverlayError, SearchPattern};
+use std::io::{Cursor, Read};
+
+/// test matches which span the boundary between base data and patches
+#[test]
+fn test_find_across_patches() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"abcabcabc"));
+    overlay.add_bytes_at(2, "X").unwrap();
+    overlay.add_bytes_at(7, "XY").unwrap();
+
+    let pattern = SearchPattern::bytes("bXa").unwrap();
+    assert_eq!(overlay.find_all(&pattern).unwrap(), vec![1..4]);
+
+    let pattern = SearchPattern::hex("?? 58").unwrap();
+    assert_eq!(overlay.find_all(&pattern).unwrap(), vec![1..3, 6..8]);
+    assert_eq!(overlay.find_in(&pattern, 2..9).unwrap(), vec![6..8]);
+}
+
+/// test matches which span the boundary between two search chunks
+#[test]
+fn test_find_across_chunks() {
+    let mut data = vec![0u8; 3 * 1024 * 1024];
+    data[1024 * 1024 - 2..1024 * 1024 + 2].copy_from_slice(b"abcd");
+    data[2 * 1024 * 1024 - 1..2 * 1024 * 1024 + 3].copy_from_slice(b"abcd");
+    let mut overlay = MemOverlay::from(Cursor::new(data));
+
+    let pattern = SearchPattern::bytes("abcd").unwrap();
+    let matches = overlay.find_all(&pattern).unwrap();
+    assert_eq!(
+        matches,
+        vec![1024 * 1024 - 2..1024 * 1024 + 2, 2 * 1024 * 1024 - 1..2 * 1024 * 1024 + 3]
+    );
+}
+
+/// test that matches do not overlap
+#[test]
+fn test_non_overlapping() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"aaaaa"));
+    let pattern = SearchPattern::bytes("aa").unwrap();
+    assert_eq!(overlay.find_all(&pattern).unwrap(), vec![0..2, 2..4]);
+}
+
+/// test search and replace
+#[test]
+fn test_replace_all() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"one two one"));
+    let pattern = SearchPattern::bytes("one").unwrap();
+    assert_eq!(overlay.replace_all(&pattern, "ONE").unwrap(), 2);
+    assert_eq!(overlay.patch_bytes(), 6);
+
+    let err = overlay.replace_all(&SearchPattern::bytes("two").unwrap(), "2").unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(
+        err,
+        OverlayError::ReplacementLengthMismatch { offset: 4, match_len: 3, replacement_len: 1 }
+    ));
+
+    let mut message = String::new();
+    overlay.read_to_string(&mut message).unwrap();
+    assert_eq!(message, "ONE two ONE");
+}
+
+/// test regular expressions
+#[cfg(feature = "regex")]
+#[test]
+fn test_regex() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"id=12, id=345, id="));
+    overlay.add_bytes_at(18, "6").unwrap();
+    let pattern = SearchPattern::regex(r"id=\d+").unwrap();
+    assert_eq!(overlay.find_all(&pattern).unwrap(), vec![0..5, 7..13, 15..19]);
+}
+
+/// test that assertions of regular expressions see the bytes beyond the
+/// boundary between two search chunks
+#[cfg(feature = "regex")]
+#[test]
+fn test_regex_across_chunks() {
+    const CHUNK: usize = 1024 * 1024;
+    let mut data = vec![b'x'; 3 * CHUNK];
+    data[0..2].copy_from_slice(b"ab");
+    data[CHUNK..CHUNK + 3].copy_from_slice(b"ab ");
+    data[CHUNK + 10..CHUNK + 14].copy_from_slice(b" ab ");
+    data[2 * CHUNK - 2..2 * CHUNK + 3].copy_from_slice(b"12345");
+    let mut overlay = MemOverlay::from(Cursor::new(data));
+    let chunk = CHUNK as u64;
+
+    let find = |overlay: &mut MemOverlay<_>, regex| overlay.find_all(&SearchPattern::regex(regex).unwrap()).unwrap();
+    assert_eq!(find(&mut overlay, r"\bab\b"), vec![chunk + 11..chunk + 13]);
+    assert_eq!(find(&mut overlay, r"^ab"), vec![0..2]);
+    assert_eq!(find(&mut overlay, r"x$"), vec![3 * chunk - 1..3 * chunk]);
+    assert_eq!(find(&mut overlay, r"\d+"), vec![2 * chunk - 2..2 * chunk + 3]);
+}
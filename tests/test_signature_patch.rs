@@ -0,0 +1,55 @@
+mod common;
+
+use common::overlay_error;
+use memoverlay::{MemOverlay, OverlayError, SearchPattern, SignaturePatch};
+use std::io::{Cursor, ErrorKind};
+
+/// test that a patch is applied to different builds of the same data
+#[test]
+fn test_relocated() {
+    let patch = SignaturePatch::new(SearchPattern::hex("de ad ?? ef").unwrap(), "XY").with_delta(-1);
+
+    let mut overlay1 = MemOverlay::from(Cursor::new(b"..\xde\xad\x00\xef.."));
+    let mut overlay2 = MemOverlay::from(Cursor::new(b".....\xde\xad\x01\xef"));
+    assert_eq!(patch.apply(&mut overlay1).unwrap(), 1);
+    assert_eq!(patch.apply(&mut overlay2).unwrap(), 4);
+    assert_eq!(overlay2.read_range(&(3..7)).unwrap(), b".XY\xad");
+}
+
+/// test that missing and ambiguous signatures are rejected
+#[test]
+fn test_not_unique() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"abcabcaa"));
+
+    let patch = SignaturePatch::new(SearchPattern::bytes("xyz").unwrap(), "!");
+    let err = patch.apply(&mut overlay).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    let patch = SignaturePatch::new(SearchPattern::bytes("abc").unwrap(), "!");
+    let err = overlay_error(patch.apply(&mut overlay).unwrap_err());
+    assert!(matches!(err, OverlayError::AmbiguousSignature { offsets } if offsets == vec![0, 3]));
+
+    // overlapping matches are ambiguous as well
+    let mut overlay = MemOverlay::from(Cursor::new(b"xaaax"));
+    let patch = SignaturePatch::new(SearchPattern::bytes("aa").unwrap(), "!");
+    let err = overlay_error(patch.apply(&mut overlay).unwrap_err());
+    assert!(matches!(err, OverlayError::AmbiguousSignature { offsets } if offsets == vec![1, 2]));
+    assert_eq!(overlay.patch_bytes(), 0);
+}
+
+/// test that a search window excludes other matches
+#[test]
+fn test_window() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"abcabcaa"));
+    let patch = SignaturePatch::new(SearchPattern::bytes("abc").unwrap(), "!").with_window(2..8);
+    assert_eq!(patch.apply(&mut overlay).unwrap(), 3);
+    assert_eq!(overlay.read_range(&(0..8)).unwrap(), b"abc!bcaa");
+
+    // the signature has been overwritten
+    let err = overlay_error(patch.locate(&mut overlay).unwrap_err());
+    assert!(matches!(err, OverlayError::SignatureNotFound));
+
+    let patch = SignaturePatch::new(SearchPattern::bytes("bca").unwrap(), "!").with_delta(-5);
+    let err = overlay_error(patch.locate(&mut overlay).unwrap_err());
+    assert!(matches!(err, OverlayError::TargetOutOfRange { offset: 4, delta: -5 }));
+}
@@ -0,0 +1,96 @@
+use memoverlay::{MemOverlay, StreamBase};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// a reader which cannot seek and returns its data in small pieces
+struct ForwardOnly {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl ForwardOnly {
+    fn new(data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            data: data.into(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ForwardOnly {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = buf.len().min(3).min(self.data.len() - self.pos);
+        buf[..length].copy_from_slice(&self.data[self.pos..self.pos + length]);
+        self.pos += length;
+        Ok(length)
+    }
+}
+
+/// test backwards seeks after the buffer has been moved into a temporary file
+#[test]
+fn test_spill_to_disk() {
+    let input: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let mut base = StreamBase::with_memory_limit(ForwardOnly::new(input.clone()), 100);
+
+    let mut dst = [0; 10];
+    base.seek(SeekFrom::Start(500)).unwrap();
+    base.read_exact(&mut dst).unwrap();
+    assert_eq!(&dst, &input[500..510]);
+    assert!(base.is_spilled());
+
+    base.seek(SeekFrom::Start(20)).unwrap();
+    base.read_exact(&mut dst).unwrap();
+    assert_eq!(&dst, &input[20..30]);
+
+    base.seek(SeekFrom::Start(0)).unwrap();
+    let mut output = Vec::new();
+    io::copy(&mut base, &mut output).unwrap();
+    assert_eq!(output, input);
+    assert_eq!(base.known_len(), Some(1000));
+}
+
+/// test an overlay whose base length is discovered while reading
+#[test]
+fn test_overlay_with_unknown_len() {
+    let base = StreamBase::new(ForwardOnly::new("hello, world!"));
+    let mut overlay = MemOverlay::with_unknown_len(base);
+    assert_eq!(overlay.base_len(), None);
+
+    overlay.add_bytes_at(7, "peter".as_bytes()).unwrap();
+
+    let mut message = String::new();
+    overlay.read_to_string(&mut message).unwrap();
+    assert_eq!(message, "hello, peter!");
+    assert_eq!(overlay.base_len(), Some(13));
+}
+
+/// test that seeking relative to the end determines the length of the base
+#[test]
+fn test_seek_from_end_with_unknown_len() {
+    let base = StreamBase::new(ForwardOnly::new("hello, world!"));
+    let mut overlay = MemOverlay::with_unknown_len(base);
+
+    assert_eq!(overlay.seek(SeekFrom::End(-5)).unwrap(), 7);
+    assert_eq!(overlay.base_len(), Some(13));
+
+    let mut dst = [0; 5];
+    overlay.read_exact(&mut dst).unwrap();
+    assert_eq!(&dst, b"world");
+}
+
+/// test that seeking forward only reads the base up to the new position
+#[test]
+fn test_seek_with_unknown_len() {
+    let input: Vec<u8> = (0..=255).cycle().take(10_000_000).collect();
+    let base = StreamBase::new(ForwardOnly::new(input.clone()));
+    let mut overlay = MemOverlay::with_unknown_len(base);
+
+    assert_eq!(overlay.seek(SeekFrom::Start(1000)).unwrap(), 1000);
+    assert_eq!(overlay.base_len(), None);
+
+    let mut dst = [0; 10];
+    overlay.read_exact(&mut dst).unwrap();
+    assert_eq!(&dst, &input[1000..1010]);
+
+    assert!(overlay.seek(SeekFrom::Start(10_000_001)).is_err());
+    assert_eq!(overlay.base_len(), Some(10_000_000));
+}
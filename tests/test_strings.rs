@@ -0,0 +1,136 @@
+mod common;
+
+use common::overlay_error;
+use memoverlay::{
+    FitPolicy, LengthPrefix, MemOverlay, OverlayError, StringFormat, TextEncoding, TooLong,
+    TooShort,
+};
+use std::io::Cursor;
+
+fn truncate() -> FitPolicy {
+    FitPolicy {
+        too_long: TooLong::Truncate,
+        ..Default::default()
+    }
+}
+
+fn exact() -> FitPolicy {
+    FitPolicy {
+        too_short: TooShort::Reject,
+        ..Default::default()
+    }
+}
+
+/// test NUL padded ASCII strings with all fit policies
+#[test]
+fn test_fixed_ascii() {
+    let mut overlay = MemOverlay::from(Cursor::new([0x20u8; 8]));
+
+    let format = StringFormat::ascii(6);
+    overlay.write_str_at(1, "abc", &format).unwrap();
+    assert_eq!(overlay.read_str_at(1, &format).unwrap(), "abc");
+    assert_eq!(overlay.read_u8_at(6).unwrap(), 0);
+    assert_eq!(overlay.read_u8_at(7).unwrap(), 0x20);
+
+    let err = overlay.write_str_at(1, "abcdefg", &format).unwrap_err();
+    assert!(matches!(
+        overlay_error(err),
+        OverlayError::StringTooLong {
+            len: 7,
+            capacity: 6
+        }
+    ));
+
+    let err = overlay
+        .write_str_at(1, "abc", &format.with_fit(exact()))
+        .unwrap_err();
+    assert!(matches!(
+        overlay_error(err),
+        OverlayError::StringTooShort { len: 3, width: 6 }
+    ));
+    overlay
+        .write_str_at(1, "uvwxyz", &format.with_fit(exact()))
+        .unwrap();
+    assert_eq!(overlay.read_str_at(1, &format).unwrap(), "uvwxyz");
+
+    overlay
+        .write_str_at(1, "abcdefg", &format.with_fit(truncate()))
+        .unwrap();
+    assert_eq!(overlay.read_str_at(1, &format).unwrap(), "abcdef");
+
+    let err = overlay.write_str_at(1, "ä", &format).unwrap_err();
+    assert!(matches!(
+        overlay_error(err),
+        OverlayError::UnencodableCharacter('ä')
+    ));
+}
+
+/// test that truncation does not split surrogate pairs
+#[test]
+fn test_utf16_truncation() {
+    let mut overlay = MemOverlay::from(Cursor::new([0xffu8; 8]));
+    let format = StringFormat::utf16le(4).with_fit(truncate());
+    overlay.write_str_at(0, "a😀b", &format).unwrap();
+    assert_eq!(overlay.read_str_at(0, &format).unwrap(), "a");
+    assert_eq!(overlay.read_u16_le_at(2).unwrap(), 0);
+    assert_eq!(overlay.read_u16_le_at(4).unwrap(), 0xffff);
+
+    overlay.write_str_at(0, "😀b", &format).unwrap();
+    assert_eq!(overlay.read_str_at(0, &format).unwrap(), "😀");
+}
+
+/// test length prefixed UTF-16 strings, where the prefix counts code units
+#[test]
+fn test_length_prefixed() {
+    let mut overlay = MemOverlay::from(Cursor::new([0u8; 16]));
+    let format = StringFormat::length_prefixed(TextEncoding::Utf16Le, LengthPrefix::U8, 10);
+    assert_eq!(overlay.write_str_at(0, "abc", &format).unwrap(), 11);
+    assert_eq!(overlay.read_u8_at(0).unwrap(), 3);
+    assert_eq!(overlay.read_str_at(0, &format).unwrap(), "abc");
+
+    overlay.write_u8_at(0, 6).unwrap();
+    let err = overlay.read_str_at(0, &format).unwrap_err();
+    assert!(matches!(overlay_error(err), OverlayError::InvalidString));
+}
+
+/// test that the fit policies behave the same for length prefixed strings
+#[test]
+fn test_length_prefixed_fit() {
+    let mut overlay = MemOverlay::from(Cursor::new([0xffu8; 8]));
+    let format = StringFormat::length_prefixed(TextEncoding::Ascii, LengthPrefix::U8, 4);
+
+    assert_eq!(overlay.write_str_at(0, "ab", &format).unwrap(), 5);
+    assert_eq!(
+        overlay.read_range(&(0..6)).unwrap(),
+        [2, b'a', b'b', 0, 0, 0xff]
+    );
+
+    let err = overlay.write_str_at(0, "abcde", &format).unwrap_err();
+    assert!(matches!(
+        overlay_error(err),
+        OverlayError::StringTooLong {
+            len: 5,
+            capacity: 4
+        }
+    ));
+
+    let err = overlay
+        .write_str_at(0, "xyz", &format.with_fit(exact()))
+        .unwrap_err();
+    assert!(matches!(
+        overlay_error(err),
+        OverlayError::StringTooShort { len: 3, width: 4 }
+    ));
+    overlay
+        .write_str_at(0, "wxyz", &format.with_fit(exact()))
+        .unwrap();
+    assert_eq!(overlay.read_str_at(0, &format).unwrap(), "wxyz");
+
+    overlay
+        .write_str_at(0, "abcde", &format.with_fit(truncate()))
+        .unwrap();
+    assert_eq!(
+        overlay.read_range(&(0..6)).unwrap(),
+        [4, b'a', b'b', b'c', b'd', 0xff]
+    );
+}
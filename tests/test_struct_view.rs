@@ -0,0 +1,37 @@
+#![cfg(feature = "zerocopy")]
+
+use memoverlay::MemOverlay;
+use std::io::Cursor;
+use zerocopy::big_endian::U32;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout)]
+#[repr(C)]
+struct Record {
+    id: U32,
+    flags: u8,
+    name: [u8; 3],
+}
+
+/// test reading and writing a struct which does not start at offset zero
+#[test]
+fn test_struct_view() {
+    let mut overlay = MemOverlay::from(Cursor::new(*b"xx\0\0\0\x07\x01abc"));
+    let mut record = overlay.view::<Record>(2);
+    let value = record.get().unwrap();
+    assert_eq!(value.id.get(), 7);
+    assert_eq!(&value.name, b"abc");
+
+    assert_eq!(record.update(|r| r.id.set(0x0107)).unwrap(), 1);
+    assert_eq!(record.update(|r| r.name = *b"abd").unwrap(), 1);
+
+    assert_eq!(overlay.patch_bytes(), 2);
+    assert_eq!(overlay.read_u32_be_at(2).unwrap(), 0x0107);
+}
+
+/// test that a struct which reaches beyond the end cannot be read
+#[test]
+fn test_truncated_struct() {
+    let mut overlay = MemOverlay::from(Cursor::new(*b"\0\0\0\x07\x01ab"));
+    assert!(overlay.view::<Record>(0).get().is_err());
+}
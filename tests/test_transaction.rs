@@ -0,0 +1,91 @@
+mod common;
+
+use common::read_all;
+use memoverlay::{ChangeEvent, ChangeKind, MemOverlay, OverlayError};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+/// test that observers are informed only after the transaction has been
+/// committed
+#[test]
+fn test_commit() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"hello, world!"[..]));
+    let events = Arc::new(Mutex::new(0));
+    let counter = Arc::clone(&events);
+    overlay.add_observer(move |_| *counter.lock().unwrap() += 1);
+
+    overlay.begin().unwrap();
+    overlay.add_bytes_at(0, "HELLO").unwrap();
+    overlay.add_bytes_at(7, "WORLD").unwrap();
+    assert_eq!(read_all(&mut overlay), b"HELLO, WORLD!");
+    assert_eq!(*events.lock().unwrap(), 0);
+
+    overlay.commit().unwrap();
+    assert!(!overlay.in_transaction());
+    assert_eq!(*events.lock().unwrap(), 2);
+    assert_eq!(read_all(&mut overlay), b"HELLO, WORLD!");
+}
+
+/// test that an aborted transaction leaves no traces
+#[test]
+fn test_abort() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"hello, world!"[..]));
+    overlay.add_bytes_at(7, "peter").unwrap();
+
+    overlay.begin().unwrap();
+    overlay.add_bytes_at(0, "HELLO").unwrap();
+    overlay.revert(7..12).unwrap();
+    overlay.abort().unwrap();
+
+    assert_eq!(read_all(&mut overlay), b"hello, peter!");
+    assert!(matches!(overlay.abort(), Err(OverlayError::NoTransaction)));
+}
+
+/// test that observers are informed about the ranges which are restored by
+/// an abort
+#[test]
+fn test_abort_events() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"hello, world!"[..]));
+    overlay.add_bytes_at(7, "peter").unwrap();
+    let events: Arc<Mutex<Vec<ChangeEvent>>> = Arc::default();
+    let recorder = Arc::clone(&events);
+    overlay.add_observer(move |event| recorder.lock().unwrap().push(event.clone()));
+
+    overlay.begin().unwrap();
+    overlay.add_bytes_at(0, "HELLO").unwrap();
+    overlay.revert(7..12).unwrap();
+    overlay.abort().unwrap();
+
+    let events = events.lock().unwrap();
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| {
+            (
+                event.kind,
+                event.range.clone(),
+                event.old_bytes.as_bytes().unwrap(),
+                event.new_bytes.as_bytes().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (ChangeKind::Abort, 0..5, &b"HELLO"[..], &b"hello"[..]),
+            (ChangeKind::Abort, 7..12, &b"world"[..], &b"peter"[..]),
+        ]
+    );
+}
+
+/// test that transactions cannot be nested
+#[test]
+fn test_nested_transaction() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"hello, world!"[..]));
+    let result = overlay.transaction(|tx| {
+        tx.add_bytes_at(0, "HELLO").unwrap();
+        tx.begin()
+    });
+    assert!(matches!(result, Err(OverlayError::TransactionActive)));
+    assert!(!overlay.in_transaction());
+    assert_eq!(read_all(&mut overlay), b"hello, world!");
+}
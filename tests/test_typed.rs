@@ -0,0 +1,50 @@
+use memoverlay::MemOverlay;
+use std::io::Cursor;
+
+/// test reading and writing values with different byte orders
+#[test]
+fn test_endianness() {
+    let mut overlay = MemOverlay::from(Cursor::new([0u8; 16]));
+    overlay.write_u32_le_at(0, 0x11223344).unwrap();
+    overlay.write_u32_be_at(4, 0x11223344).unwrap();
+    overlay.write_i64_le_at(8, -2).unwrap();
+
+    assert_eq!(overlay.read_u32_le_at(0).unwrap(), 0x11223344);
+    assert_eq!(overlay.read_u32_be_at(0).unwrap(), 0x44332211);
+    assert_eq!(overlay.read_u16_be_at(4).unwrap(), 0x1122);
+    assert_eq!(overlay.read_i64_le_at(8).unwrap(), -2);
+    assert_eq!(overlay.read_u8_at(15).unwrap(), 0xff);
+
+    overlay.write_f64_be_at(8, 1.5).unwrap();
+    assert_eq!(overlay.read_f64_be_at(8).unwrap(), 1.5);
+    assert!(overlay.read_u32_le_at(14).is_err());
+}
+
+/// test that only changed bytes are patched
+#[test]
+fn test_minimal_patches() {
+    let mut overlay = MemOverlay::from(Cursor::new([0x78, 0x56, 0x34, 0x12]));
+    overlay.write_u32_le_at(0, 0x12345678).unwrap();
+    assert_eq!(overlay.patch_bytes(), 0);
+
+    overlay.write_u32_le_at(0, 0xff34ff78).unwrap();
+    assert_eq!(overlay.patch_bytes(), 2);
+    assert_eq!(overlay.read_u32_le_at(0).unwrap(), 0xff34ff78);
+}
+
+/// test LEB128 values
+#[test]
+fn test_leb128() {
+    let mut overlay = MemOverlay::from(Cursor::new([0u8; 32]));
+    for (value, length) in [(0i64, 1), (63, 1), (64, 2), (-64, 1), (-65, 2), (i64::MIN, 10), (i64::MAX, 10)] {
+        assert_eq!(overlay.write_sleb128_at(4, value).unwrap(), length);
+        assert_eq!(overlay.read_sleb128_at(4).unwrap(), (value, length));
+    }
+    for (value, length) in [(0u64, 1), (127, 1), (128, 2), (u64::MAX, 10)] {
+        assert_eq!(overlay.write_uleb128_at(4, value).unwrap(), length);
+        assert_eq!(overlay.read_uleb128_at(4).unwrap(), (value, length));
+    }
+
+    overlay.add_bytes_at(0, [0xff; 11]).unwrap();
+    assert!(overlay.read_uleb128_at(0).is_err());
+}
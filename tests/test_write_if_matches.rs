@@ -0,0 +1,41 @@
+mod common;
+
+use common::overlay_error;
+use memoverlay::{MemOverlay, OverlayError};
+use std::io::{Cursor, ErrorKind};
+
+/// test that matching bytes are replaced
+#[test]
+fn test_matching_write() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"\x74\x05\x90\x90"));
+    overlay.write_if_matches(0, [0x74, 0x05], [0xeb]).unwrap();
+    overlay.write_if_matches(1, [0x05, 0x90], [0x06, 0x91]).unwrap();
+    assert_eq!(overlay.read_range(&(0..4)).unwrap(), [0xeb, 0x06, 0x91, 0x90]);
+}
+
+/// test that a mismatch reports the actual bytes and changes nothing
+#[test]
+fn test_mismatch() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"\x75\x05\x90\x90"));
+    let err = overlay.write_if_matches(0, [0x74, 0x05], [0xeb]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    let err = overlay_error(err);
+    match err {
+        OverlayError::UnexpectedBytes { offset, expected, actual } => {
+            assert_eq!(offset, 0);
+            assert_eq!(expected, [0x74, 0x05]);
+            assert_eq!(actual, [0x75, 0x05]);
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    assert_eq!(overlay.patch_bytes(), 0);
+}
+
+/// test that expected bytes beyond the end of the data do not match
+#[test]
+fn test_mismatch_at_end() {
+    let mut overlay = MemOverlay::from(Cursor::new(b"abc"));
+    let err = overlay.write_if_matches(2, "cd", "xy").unwrap_err();
+    let err = overlay_error(err);
+    assert!(matches!(err, OverlayError::UnexpectedBytes { actual, .. } if actual == b"c"));
+}
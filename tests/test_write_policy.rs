@@ -0,0 +1,83 @@
+mod common;
+
+use common::{overlay_error, read_all};
+use memoverlay::{MemOverlay, OverlayError, WritePolicy};
+use std::io::Cursor;
+
+/// test that overlapping writes are rejected and do not change any data
+#[test]
+fn test_reject_overlap() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"hello, world!"[..]));
+    overlay.set_write_policy(WritePolicy {
+        allow_overlap: false,
+        ..Default::default()
+    });
+
+    overlay.add_bytes_at(7, "peter").unwrap();
+    overlay.add_bytes_at(0, "HELLO").unwrap();
+    let err = overlay.add_bytes_at(4, "XXXX").unwrap_err();
+    assert!(matches!(
+        overlay_error(err),
+        OverlayError::OverlappingWrite { offset: 4, len: 4 }
+    ));
+    assert_eq!(read_all(&mut overlay), b"HELLO, peter!");
+}
+
+/// test that writes beyond the end of the base can be forbidden
+#[test]
+fn test_reject_growth() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123456789"[..]));
+    overlay.set_write_policy(WritePolicy {
+        allow_growth: false,
+        ..Default::default()
+    });
+
+    overlay.add_bytes_at(8, [0xff, 0xff]).unwrap();
+    let err = overlay.add_bytes_at(9, [0xff, 0xff]).unwrap_err();
+    assert!(matches!(
+        overlay_error(err),
+        OverlayError::GrowthForbidden { offset: 9, len: 2, base_len: 10 }
+    ));
+}
+
+/// test the limit of bytes which are stored in patches
+#[test]
+fn test_patch_limit() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123456789"[..]));
+    overlay.set_write_policy(WritePolicy {
+        max_patch_bytes: Some(4),
+        ..Default::default()
+    });
+
+    overlay.add_bytes_at(0, "ab").unwrap();
+    overlay.add_bytes_at(1, "cd").unwrap();
+    assert_eq!(overlay.patch_bytes(), 4);
+    let err = overlay.add_bytes_at(5, "e").unwrap_err();
+    assert!(matches!(
+        overlay_error(err),
+        OverlayError::PatchLimitExceeded { required: 5, limit: 4 }
+    ));
+}
+
+/// test writes behind the end of the data
+#[test]
+fn test_gap() {
+    let mut overlay = MemOverlay::from(Cursor::new(&b"0123"[..]));
+    let err = overlay.add_bytes_at(6, "xy").unwrap_err();
+    assert!(matches!(
+        overlay_error(err),
+        OverlayError::WriteBeyondEnd { offset: 6, end: 4 }
+    ));
+
+    // appending directly at the end does not leave a gap
+    overlay.add_bytes_at(4, "ab").unwrap();
+    assert_eq!(read_all(&mut overlay), b"0123ab");
+
+    overlay.set_write_policy(WritePolicy {
+        allow_gap: true,
+        ..Default::default()
+    });
+    overlay.add_bytes_at(8, "xy").unwrap();
+    assert_eq!(overlay.data_len(), 10);
+    assert_eq!(read_all(&mut overlay), b"0123ab\0\0xy");
+}